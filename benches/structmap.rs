@@ -0,0 +1,51 @@
+//! Compares a type-only struct query with the field name FST left unbuilt, as `StructDefMap` does
+//! since it builds it lazily, to the same query with the FST built up front, as it used to be.
+//!
+//! Run with `cargo bench --bench structmap`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use roogle::index::Index;
+use roogle::structmap::StructDefMap;
+
+const STRUCTS: usize = 20_000;
+const FIELDS: usize = 8;
+const TYPES: [&str; FIELDS] = ["u8", "u32", "String", "Vec<u8>", "Option<u64>", "Duration", "bool", "HashMap<String, u32>"];
+
+/// `STRUCTS` structs of `FIELDS` fields each, every field name distinct, spread over 100 files.
+fn corpus() -> Index<'static> {
+    Index::from_sources((0..100).map(|file| {
+        let code = (0..STRUCTS / 100).map(|i| {
+            let fields = TYPES.iter().enumerate().map(|(j, ty)| format!("field_{file}_{i}_{j}: {ty}")).collect::<Vec::<_>>();
+            format!("struct S{file}_{i} {{ {fields} }}\n", fields = fields.join(", "))
+        }).collect();
+        (format!("{file}.rs").into(), code)
+    }))
+}
+
+fn map<'a>(index: &'a Index<'static>) -> StructDefMap<'a> {
+    let mut map = StructDefMap::new(STRUCTS);
+    index.files.iter().flat_map(|(_, defs, ..)| defs).for_each(|(loc, def)| map.insert(def, loc));
+    map
+}
+
+fn type_only(c: &mut Criterion) {
+    let index = corpus();
+    let mut group = c.benchmark_group("type-only query");
+    group.sample_size(20);
+    // The maps are built outside of the measurement, only what the query itself costs is timed.
+    group.bench_function("lazy fst", |b| b.iter_batched(|| map(&index), |map| {
+        black_box(map.find_types("duration", None).len())
+    }, BatchSize::LargeInput));
+    group.bench_function("eager fst", |b| b.iter_batched(|| map(&index), |map| {
+        // A name search builds the FST, like `finalize` did before every search.
+        black_box(map.find_names("", None).len());
+        black_box(map.find_types("duration", None).len())
+    }, BatchSize::LargeInput));
+    group.finish()
+}
+
+criterion_group!(benches, type_only);
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+use proc_macro2::TokenTree;
+use syn::{Meta, Path, Token, Attribute, punctuated::Punctuated};
+
+use crate::to_static_str;
+
+/// The attributes of an indexed item that `--no-hidden` and `--no-deprecated` filter on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Attrs {
+    /// `#[doc(hidden)]`
+    pub hidden: bool,
+    /// `#[deprecated]`, with or without a note.
+    pub deprecated: bool
+}
+
+impl Attrs {
+    /// Checks whether the item is dropped from the results by `--no-hidden` or `--no-deprecated`.
+    #[inline]
+    pub fn filtered_out(&self, no_hidden: bool, no_deprecated: bool) -> bool {
+        (no_hidden && self.hidden) || (no_deprecated && self.deprecated)
+    }
+}
+
+impl From::<&[Attribute]> for Attrs {
+    fn from(attrs: &[Attribute]) -> Self {
+        attrs.iter().fold(Self::default(), |acc, attr| Self {
+            hidden: acc.hidden || is_doc_hidden(attr),
+            deprecated: acc.deprecated || attr.path().is_ident("deprecated")
+        })
+    }
+}
+
+/// `hidden` can be anywhere in the list, like in `#[doc(alias = "x", hidden)]`.
+fn is_doc_hidden(attr: &Attribute) -> bool {
+    let Meta::List(ref list) = attr.meta else { return false };
+    list.path.is_ident("doc") && list.tokens.clone().into_iter().any(|tt| {
+        matches!(tt, TokenTree::Ident(ident) if ident == "hidden")
+    })
+}
+
+/// The std traits that can be derived, lowercased like indexed names: a type deriving one of
+/// them implements it. Other derive macros are not assumed to implement the trait they're
+/// named after, if any.
+pub const DERIVABLE: [&str; 9] = ["clone", "copy", "debug", "default", "hash", "partialeq", "eq", "partialord", "ord"];
+
+/// The traits of the `#[derive]` attributes in `attrs`, by the last segment of their path, so
+/// `#[derive(serde::Serialize)]` gives `serialize`.
+pub fn derives(attrs: &[Attribute]) -> Vec::<&'static str> {
+    attrs.iter()
+        .filter(|attr| attr.path().is_ident("derive"))
+        .filter_map(|attr| attr.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated).ok())
+        .flatten()
+        .filter_map(|path| path.segments.last().map(|seg| to_static_str(&seg.ident)))
+        .collect()
+}
@@ -0,0 +1,84 @@
+use std::io;
+use std::fs::{self, read_to_string};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const CACHE_PATH: &str = ".roogle-cache";
+
+const MAGIC: &str = "roogle-cache 1";
+
+/// Maps each indexed file to its modification time and its source with function bodies stripped.
+/// Stripped sources keep every item on its original line and column, so parsing them yields the
+/// same `Loc`s as parsing the original files, just much faster.
+#[derive(Default)]
+pub struct Cache {
+    entries: HashMap::<PathBuf, (Duration, String)>
+}
+
+#[inline]
+fn mtime_since_epoch(mtime: SystemTime) -> Duration {
+    mtime.duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+#[inline]
+pub fn modified(path: &Path) -> Option::<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl Cache {
+    pub fn load<P: AsRef::<Path>>(path: P) -> io::Result::<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "malformed cache");
+
+        let contents = read_to_string(path)?;
+        let Some(mut rest) = contents.strip_prefix(MAGIC).and_then(|r| r.strip_prefix('\n')) else {
+            return Err(invalid())
+        };
+
+        let mut entries = HashMap::new();
+        while !rest.is_empty() {
+            let (header, tail) = rest.split_once('\n').ok_or_else(invalid)?;
+            let mut parts = header.splitn(4, ' ');
+            let mut next_num = || parts.next().and_then(|p| p.parse::<u64>().ok()).ok_or_else(invalid);
+            let (secs, nanos, len) = (next_num()?, next_num()?, next_num()? as usize);
+            let path = parts.next().ok_or_else(invalid)?;
+            let code = tail.get(..len).ok_or_else(invalid)?;
+            let mtime = Duration::new(secs, nanos as u32);
+            entries.insert(PathBuf::from(path), (mtime, code.to_owned()));
+            rest = tail[len..].strip_prefix('\n').ok_or_else(invalid)?;
+        }
+
+        Ok(Self {entries})
+    }
+
+    pub fn write<P: AsRef::<Path>>(&self, path: P) -> io::Result::<()> {
+        let mut out = String::with_capacity(self.entries.values().map(|(_, code)| code.len() + 64).sum());
+        out.push_str(MAGIC);
+        out.push('\n');
+        self.entries.iter().for_each(|(path, (mtime, code))| {
+            out.push_str(&format!{
+                "{secs} {nanos} {len} {path}\n",
+                secs = mtime.as_secs(),
+                nanos = mtime.subsec_nanos(),
+                len = code.len(),
+                path = path.display()
+            });
+            out.push_str(code);
+            out.push('\n');
+        });
+        fs::write(path, out)
+    }
+
+    /// Returns the stripped source of `path` if it was cached when the file had modification time `mtime`.
+    #[inline]
+    pub fn get(&self, path: &Path, mtime: SystemTime) -> Option::<&str> {
+        self.entries.get(path).and_then(|(cached, code)| {
+            (*cached == mtime_since_epoch(mtime)).then_some(code.as_str())
+        })
+    }
+
+    #[inline]
+    pub fn insert(&mut self, path: PathBuf, mtime: SystemTime, code: String) {
+        self.entries.insert(path, (mtime_since_epoch(mtime), code));
+    }
+}
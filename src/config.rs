@@ -0,0 +1,669 @@
+use std::io;
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::fs::read_to_string;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::results::Kind;
+use crate::template::Template;
+
+use regex_lite::{Regex, RegexBuilder};
+
+pub const OPTIONS: &str = "\
+options:
+    --query-file <PATH>    read the query from <PATH> instead of the command line, or from stdin
+                           if <PATH> is `-`, the query can span lines and have `//` comments
+    --by-file              print the number of matches per file instead of each match
+    --ignore-refs          match `&T`, `&mut T` and `T` interchangeably
+    --strict-mut           keep `&mut T` apart from `T` and `&T` where references are ignored, by
+                           `--ignore-refs` or the `&` query modifier
+    --loose-inputs         like `--ignore-refs`, for the arguments of function queries only
+    --loose-output         like `--ignore-refs`, for the return type of function queries only
+    --unwrap-ptrs          match `Box<T>`, `Rc<T>`, `Arc<T>` and `T` interchangeably
+    --arrays-as-slices     match `&[T; N]` as `&[T]`, like the array coerces to, dropping its length
+    --repl                 build the index once, then read queries from stdin until EOF, with the
+                           `:stats`, `:reload`, to read the files found at startup again, `:rescan`,
+                           to walk the directory again too, and `:quit` commands
+    --build-index          parse every file, write the index cache and exit without a query
+    --full-parse           parse the bodies of functions too, which are otherwise stripped from the
+                           code before it's parsed, only signatures being indexed
+    --rustdoc-json <PATH>  search the items of a rustdoc JSON file instead of the `.rs` files,
+                           can be given multiple times
+    --match-arg-names      make the named arguments of a function query match only arguments of the
+                           same name, like `timeout` in `fn(timeout: Duration)`, `_` matching any
+    --min-args <N>         only match functions taking at least <N> arguments
+    --max-args <N>         only match functions taking at most <N> arguments
+    --generic-only         only match functions with type parameters, their `impl` block's included
+    --non-generic-only     only match functions without type parameters
+    --returns-ref          only match functions returning a reference, with `--ignore-refs` the
+                           query's return type is then the referenced type, like `-> Config`
+    --associated-fns-only  only match the associated functions of `impl` blocks that take no `self`,
+                           like constructors, leaving out methods and free functions
+    --fallible             only match functions returning a `Result`, compared by the last segment of
+                           its path so that aliases like `io::Result<T>` count too
+    --infallible           only match functions not returning a `Result`
+    --max-type-depth <N>   give up comparing types nested deeper than <N>, 32 by default, which then
+                           don't match, `--verbose` reports how many comparisons were given up
+    --count-self           count the `self` receiver as an argument in `--min-args`/`--max-args`
+    --ordered-fields       only match structs having the fields of a struct query in the same order,
+                           every field of the query has to be named
+    --strict-variants      only match enums having the variants of an enum query by name and kind,
+                           tuple, struct or unit, with their fields, so `V(Duration)` doesn't match
+                           `V { t: Duration }` as it does by default
+    --kind <KINDS>         only match items of the comma-separated <KINDS>: `fn`, `struct`, `enum`,
+                           `const`, `trait`, `macro` and `type`, `--types` and `--top-types`
+                           included
+    --not-kind <KINDS>     only match items not of the comma-separated <KINDS>
+    --flat                 print all matches in one list instead of grouping them by item kind
+    --top-types <N>        print the <N> types used the most by function arguments, return types
+                           and struct and enum fields, with their counts, instead of running a query
+    --types                print every distinct type used by function arguments, return types and
+                           struct and enum fields, sorted, one per line, instead of running a query
+    --variant <NAME>       find the enums having a variant named <NAME>, instead of running a query
+    --variant-type <TYPE>  find the enums having a variant with a field of type <TYPE>, spelled
+                           the way the enums spell it, instead of running a query
+    --constructors <TYPE>  find the constructors of <TYPE>, instead of running a query: its associated
+                           functions without `self` returning `Self` or <TYPE>, possibly in an
+                           `Option` or a `Result`, those taking the fewest arguments first
+    --similar <PATH:LINE>  find the items like the function, struct or enum starting at line <LINE>
+                           of the file at <PATH>, or the innermost one around it, instead of running
+                           a query: functions of the same signature, then of the same one ignoring
+                           the return type, references and argument order, and structs and enums
+                           sharing the most field types, variant names and field names with it
+    --data-variants <N>    only match enums with at least <N> variants carrying data, without a query
+                           list them
+    --no-impl-methods      only index free functions, skipping the methods of all `impl` blocks,
+                           trait impls included
+    --json                 print the matches as a JSON array, with the byte offsets of each item
+    --format <FORMAT>      `text`, the default, `json`, like `--json`, `jsonl`: one JSON object per
+                           line and match, once the search is done, `json-full`: one JSON
+                           object with the normalized query, the matches with their signatures and
+                           crates and the search statistics, or `quickfix`: one
+                           `path:line:column: kind name` line per match, the column 1-based, for
+                           the quickfix list of editors
+    --porcelain            print one line per match with the tab-separated `kind`, `path`, `line`,
+                           `column`, `name` and `signature`, `-` for a missing name, a format that
+                           stays the same across versions, for scripts
+    --picker               print one `signature<TAB>path:line:column` line per match, tabs and line
+                           breaks in the signature escaped and the column 1-based, to pipe into a
+                           fuzzy picker like `fzf` and jump to the line picked
+    --template <TEMPLATE>  print one line per match made from <TEMPLATE>, where `{path}`, `{line}`,
+                           `{column}`, 0-based, `{col}`, 1-based, `{kind}`, `{name}`, `{sig}`,
+                           `{crate}` and `{reason}`, with `--explain`, stand for the values of the
+                           match, empty when it has none, `{{` and `}}` for braces and `\\t` and
+                           `\\n` for a tab and a line break, like `{path}:{line}:{col} {kind} {name}`
+    --suggest              when a function query matches nothing, retry it ignoring the return type,
+                           then references, then argument order, and report the first that matches
+    --invert               print the items of the queried kind that do not match the query instead,
+                           filters like `--min-args` still apply
+    --limit <N>            print at most <N> matches
+    --max-results-per-file <N>
+                           print at most <N> matches per file, noting how many more each file had
+    --lossy                index files that are not valid UTF-8 with the invalid bytes replaced,
+                           instead of skipping them
+    --highlight            print the signatures of matched functions and structs, in bold where they
+                           matched the query when printing to a terminal and `NO_COLOR` is unset,
+                           methods after the header of their `impl` block, like `impl<t> repo<t> ::`
+    --explain              print why each match matched on a line under it, like the types the
+                           query's variables took or how the query was loosened
+    --workspace            search the source directories of the packages of the Cargo workspace the
+                           current directory is in, as `cargo metadata` lists them, instead of
+                           walking the current directory
+    --file <PATH>          index the file at <PATH> only instead of walking the current directory
+    --line-range <START:END>
+                           with `--file`, only match the items starting between the lines <START>
+                           and <END> of it, both included and counted from 1
+    --after <TIME>         only index the files modified after <TIME>, a `YYYY-MM-DD` date, in UTC,
+                           or a time ago like `30m`, `12h`, `7d` or `2w`
+    --before <TIME>        only index the files modified before <TIME>, written like for `--after`
+    --extract-markdown     also index the fenced Rust code blocks of `.md` and `.markdown` files,
+                           at their lines in those files
+    --skip-dir <NAME>      do not descend into directories named <NAME>, like `vendor`, can be
+                           given multiple times
+    --hidden               also walk the files and directories whose name starts with a `.`, like
+                           `.cargo`, which are skipped by default
+    --include-generated    also index generated files: the ones under `target` and the ones with a
+                           `// @generated` or `// Code generated` comment in their first lines
+    --no-generated         skip generated files, the default
+    --bound <TRAIT>        only match functions, structs and enums with a type parameter bounded by
+                           <TRAIT>, compared by the last segment of its path, like `Serialize`
+    --derives <TRAIT>      only match structs and enums deriving <TRAIT>, compared by the last segment
+                           of its path, without a query list them
+    --name-regex <REGEX>   only match items whose name matches <REGEX>, case-insensitively, like
+                           `^get_`, without a query list them
+    --equiv <PATH>         make the types of each `A = B` line of the file at <PATH> interchangeable,
+                           like a domain alias and the type it stands for, `#` starting comments,
+                           a side like `Map<K>`, whose type arguments are single letters, being an
+                           alias expanded with the arguments it's given, like `Map<String>`
+    --expand-aliases       make the type aliases of the searched code interchangeable with the types
+                           they stand for, like the lines of an `--equiv` file
+    --no-std-paths         match the full paths of well-known std types, like `std::string::String`,
+                           only as written instead of as their bare names
+    --suffix-paths         make the paths of function queries match any path they end, so
+                           `io::Error` matches `std::io::Error` and `tokio::io::Error`
+    --no-hidden            skip `#[doc(hidden)]` items
+    --no-deprecated        skip `#[deprecated]` items
+    --exclude-file <PATH>  drop the matches in the file at <PATH>, like the one an editor runs the
+                           query from, can be given multiple times
+    --crate <NAME>         only match items of the crate named <NAME> in the `[package]` of the
+                           nearest `Cargo.toml` above their file, for directories holding several
+    --relative-to <DIR>    print the paths of the matches relative to <DIR>, or absolute for the files
+                           that are not under it
+    --machine-summary      also print `roogle: files=N skipped=N matches=N elapsed_ms=N` to stderr
+                           after the results, for scripts
+    --sort-by <KEY>        sort matches by `path` (the default), `name` or `kind`
+    -v, --verbose          report files that could not be read or failed to parse, and how many of
+                           their items were recovered";
+
+pub const MODIFIERS: &str = "\
+query modifiers, written before the query, like `~&fn(Config) -> bool`:
+    ~                      like `--suggest`, for this query only
+    !                      like `--invert`, for this query only
+    &                      like `--ignore-refs`, for this function query only";
+
+static CONFIG: OnceLock::<Config> = OnceLock::new();
+
+#[cfg(test)]
+thread_local! {
+    /// The config of the test running on this thread, see `with_config`.
+    static TEST_CONFIG: std::cell::Cell::<Option::<&'static Config>> = const { std::cell::Cell::new(None) };
+}
+
+/// Returns the global config, or the default one if `Config::init` was never called.
+#[inline]
+pub fn config() -> &'static Config {
+    #[cfg(test)]
+    if let Some(config) = TEST_CONFIG.get() { return config }
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Runs `f` with `config()` returning the config parsed from `args` on this thread, for tests of
+/// options, which can't set the global config as tests share it.
+#[cfg(test)]
+pub fn with_config<T>(args: &[&str], f: impl FnOnce() -> T) -> T {
+    let config = Config::parse(args.iter().map(|arg| arg.to_string())).unwrap();
+    let previous = TEST_CONFIG.replace(Some(Box::leak(Box::new(config))));
+    let result = f();
+    TEST_CONFIG.set(previous);
+    result
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    #[default]
+    Path,
+    Name,
+    Kind
+}
+
+impl SortBy {
+    fn parse(key: &str) -> Result::<Self, String> {
+        match key {
+            "path" => Ok(Self::Path),
+            "name" => Ok(Self::Name),
+            "kind" => Ok(Self::Kind),
+            _ => Err(format!("invalid sort key: `{key}`, expected `path`, `name` or `kind`"))
+        }
+    }
+}
+
+fn parse_kinds(option: &str, value: Option::<String>) -> Result::<Vec::<Kind>, String> {
+    let value = value.ok_or_else(|| format!("`{option}` expects comma-separated item kinds"))?;
+    value.split(',').map(|kind| Kind::parse(kind.trim()).ok_or_else(|| {
+        format!("invalid kind: `{kind}`, expected `fn`, `struct`, `enum`, `const`, `trait`, `macro` or `type`")
+    })).collect()
+}
+
+fn parse_number(option: &str, value: Option::<String>) -> Result::<usize, String> {
+    let value = value.ok_or_else(|| format!("`{option}` expects a number"))?;
+    value.parse().map_err(|_| format!("`{option}` expects a number, got `{value}`"))
+}
+
+fn parse_line_range(value: Option::<String>) -> Result::<(usize, usize), String> {
+    let value = value.ok_or("`--line-range` expects `START:END`")?;
+    let range = value.split_once(':').and_then(|(start, end)| Some((start.parse().ok()?, end.parse().ok()?)));
+    match range {
+        Some((start, end)) if start >= 1 && start <= end => Ok((start, end)),
+        Some(..) => Err(format!("invalid line range: `{value}`, expected lines from 1 with START not past END")),
+        None => Err(format!("`--line-range` expects `START:END`, got `{value}`"))
+    }
+}
+
+/// Parses the `PATH:LINE` of `--similar`, the path being everything before the last `:`.
+fn parse_location(value: Option::<String>) -> Result::<(PathBuf, usize), String> {
+    let value = value.ok_or("`--similar` expects `PATH:LINE`")?;
+    match value.rsplit_once(':').map(|(path, line)| (path, line.parse::<usize>())) {
+        Some((path, Ok(line))) if !path.is_empty() && line >= 1 => Ok((PathBuf::from(path), line)),
+        _ => Err(format!("`--similar` expects `PATH:LINE`, lines from 1, got `{value}`"))
+    }
+}
+
+/// Parses the time of `--after` and `--before`: a `YYYY-MM-DD` date, at midnight UTC, or a
+/// number of seconds, minutes, hours, days or weeks ago, like `7d`.
+fn parse_time(option: &str, value: Option::<String>) -> Result::<SystemTime, String> {
+    let value = value.ok_or_else(|| format!("`{option}` expects a date or a time ago"))?;
+    let invalid = || format!("invalid time: `{value}`, expected a `YYYY-MM-DD` date or a time ago like `7d`");
+    if let Some(unit) = value.chars().last().filter(char::is_ascii_alphabetic) {
+        let count = value[..value.len() - 1].parse::<u64>().map_err(|_| invalid())?;
+        let secs = match unit {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(invalid())
+        };
+        return SystemTime::now().checked_sub(Duration::from_secs(count.saturating_mul(secs))).ok_or_else(invalid)
+    }
+    let date = value.splitn(3, '-').map(|part| part.parse::<i64>().ok()).collect::<Option::<Vec::<_>>>();
+    let Some(&[year, month, day]) = date.as_deref() else { return Err(invalid()) };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) { return Err(invalid()) }
+    let days = u64::try_from(days_from_civil(year, month, day)).map_err(|_| invalid())?;
+    Ok(UNIX_EPOCH + Duration::from_secs(days * 24 * 60 * 60))
+}
+
+/// The number of days from 1970-01-01 to the date of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+    pub query: Option::<String>,
+    pub query_file: Option::<PathBuf>,
+    pub by_file: bool,
+    pub ignore_refs: bool,
+    pub strict_mut: bool,
+    pub loose_inputs: bool,
+    pub loose_output: bool,
+    pub unwrap_ptrs: bool,
+    pub arrays_as_slices: bool,
+    pub build_index: bool,
+    pub full_parse: bool,
+    pub repl: bool,
+    pub rustdoc_json: Vec::<PathBuf>,
+    pub match_arg_names: bool,
+    pub min_args: Option::<usize>,
+    pub max_args: Option::<usize>,
+    pub count_self: bool,
+    pub returns_ref: bool,
+    pub associated_fns_only: bool,
+    pub max_type_depth: Option::<usize>,
+    pub fallible: bool,
+    pub infallible: bool,
+    pub generic_only: bool,
+    pub non_generic_only: bool,
+    pub ordered_fields: bool,
+    pub strict_variants: bool,
+    pub kinds: Vec::<Kind>,
+    pub not_kinds: Vec::<Kind>,
+    pub flat: bool,
+    pub json: bool,
+    pub json_lines: bool,
+    pub porcelain: bool,
+    pub quickfix: bool,
+    pub picker: bool,
+    pub template: Option::<Template>,
+    pub json_full: bool,
+    pub suggest: bool,
+    pub invert: bool,
+    pub lossy: bool,
+    pub highlight: bool,
+    pub explain: bool,
+    pub include_generated: bool,
+    pub workspace: bool,
+    pub file: Option::<PathBuf>,
+    pub line_range: Option::<(usize, usize)>,
+    pub after: Option::<SystemTime>,
+    pub before: Option::<SystemTime>,
+    pub extract_markdown: bool,
+    pub skip_dirs: Vec::<String>,
+    pub hidden: bool,
+    pub no_hidden: bool,
+    pub bound: Option::<String>,
+    pub derives: Option::<String>,
+    pub name_regex: Option::<Regex>,
+    pub no_std_paths: bool,
+    pub suffix_paths: bool,
+    pub equiv: Option::<PathBuf>,
+    pub expand_aliases: bool,
+    pub no_deprecated: bool,
+    pub exclude_files: Vec::<PathBuf>,
+    pub crate_name: Option::<String>,
+    pub relative_to: Option::<PathBuf>,
+    pub limit: Option::<usize>,
+    pub max_results_per_file: Option::<usize>,
+    pub no_impl_methods: bool,
+    pub variant: Option::<String>,
+    pub top_types: Option::<usize>,
+    pub types: bool,
+    pub variant_type: Option::<String>,
+    pub data_variants: Option::<usize>,
+    pub constructors: Option::<String>,
+    pub similar: Option::<(PathBuf, usize)>,
+    pub sort_by: SortBy,
+    pub machine_summary: bool,
+    pub verbose: bool
+}
+
+impl Config {
+    pub fn parse<I>(mut args: I) -> Result::<Self, String>
+    where
+        I: Iterator<Item = String>
+    {
+        let mut config = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--query-file" => {
+                    let path = args.next().ok_or("`--query-file` expects a path")?;
+                    config.query_file = Some(PathBuf::from(path))
+                }
+                "--by-file" => config.by_file = true,
+                "--ignore-refs" => config.ignore_refs = true,
+                "--strict-mut" => config.strict_mut = true,
+                "--loose-inputs" => config.loose_inputs = true,
+                "--loose-output" => config.loose_output = true,
+                "--unwrap-ptrs" => config.unwrap_ptrs = true,
+                "--arrays-as-slices" => config.arrays_as_slices = true,
+                "--build-index" => config.build_index = true,
+                "--full-parse" => config.full_parse = true,
+                "--repl" => config.repl = true,
+                "--rustdoc-json" => {
+                    let path = args.next().ok_or("`--rustdoc-json` expects a path")?;
+                    config.rustdoc_json.push(PathBuf::from(path))
+                }
+                "--match-arg-names" => config.match_arg_names = true,
+                "--min-args" => config.min_args = Some(parse_number(&arg, args.next())?),
+                "--max-args" => config.max_args = Some(parse_number(&arg, args.next())?),
+                "--top-types" => config.top_types = Some(parse_number(&arg, args.next())?),
+                "--types" => config.types = true,
+                "--data-variants" => config.data_variants = Some(parse_number(&arg, args.next())?),
+                "--limit" => config.limit = Some(parse_number(&arg, args.next())?),
+                "--max-results-per-file" => config.max_results_per_file = Some(parse_number(&arg, args.next())?),
+                "--count-self" => config.count_self = true,
+                "--returns-ref" => config.returns_ref = true,
+                "--max-type-depth" => config.max_type_depth = Some(parse_number(&arg, args.next())?),
+                "--associated-fns-only" => config.associated_fns_only = true,
+                "--fallible" => config.fallible = true,
+                "--infallible" => config.infallible = true,
+                "--generic-only" => config.generic_only = true,
+                "--non-generic-only" => config.non_generic_only = true,
+                "--ordered-fields" => config.ordered_fields = true,
+                "--strict-variants" => config.strict_variants = true,
+                "--kind" => config.kinds.extend(parse_kinds(&arg, args.next())?),
+                "--not-kind" => config.not_kinds.extend(parse_kinds(&arg, args.next())?),
+                "--flat" => config.flat = true,
+                "--json" => config.json = true,
+                "--format" => match args.next().as_deref() {
+                    Some("text") => (config.json, config.json_lines, config.json_full, config.quickfix) = (false, false, false, false),
+                    Some("json") => (config.json, config.json_lines, config.json_full, config.quickfix) = (true, false, false, false),
+                    Some("jsonl") => (config.json, config.json_lines, config.json_full, config.quickfix) = (false, true, false, false),
+                    Some("json-full") => (config.json, config.json_lines, config.json_full, config.quickfix) = (false, false, true, false),
+                    Some("quickfix") => (config.json, config.json_lines, config.json_full, config.quickfix) = (false, false, false, true),
+                    Some(format) => return Err(format!("invalid format: `{format}`, expected `text`, `json`, `jsonl`, `json-full` or `quickfix`")),
+                    None => return Err("`--format` expects `text`, `json`, `jsonl`, `json-full` or `quickfix`".to_owned())
+                }
+                "--porcelain" => config.porcelain = true,
+                "--picker" => config.picker = true,
+                "--template" => {
+                    let template = args.next().ok_or("`--template` expects a template")?;
+                    config.template = Some(Template::parse(&template)?)
+                }
+                "--suggest" => config.suggest = true,
+                "--invert" => config.invert = true,
+                "--lossy" => config.lossy = true,
+                "--highlight" => config.highlight = true,
+                "--explain" => config.explain = true,
+                "--skip-dir" => {
+                    let name = args.next().ok_or("`--skip-dir` expects a directory name")?;
+                    config.skip_dirs.push(name)
+                }
+                "--workspace" => config.workspace = true,
+                "--file" => {
+                    let path = args.next().ok_or("`--file` expects a path")?;
+                    config.file = Some(PathBuf::from(path))
+                }
+                "--line-range" => config.line_range = Some(parse_line_range(args.next())?),
+                "--after" => config.after = Some(parse_time(&arg, args.next())?),
+                "--before" => config.before = Some(parse_time(&arg, args.next())?),
+                "--extract-markdown" => config.extract_markdown = true,
+                "--hidden" => config.hidden = true,
+                "--include-generated" => config.include_generated = true,
+                "--no-generated" => config.include_generated = false,
+                "--bound" => {
+                    let bound = args.next().ok_or("`--bound` expects a trait")?;
+                    let name = bound.rsplit("::").next().unwrap_or_default().trim().to_lowercase();
+                    config.bound = Some(name)
+                }
+                "--derives" => {
+                    let derive = args.next().ok_or("`--derives` expects a trait")?;
+                    let name = derive.rsplit("::").next().unwrap_or_default().trim().to_lowercase();
+                    config.derives = Some(name)
+                }
+                "--name-regex" => {
+                    let pattern = args.next().ok_or("`--name-regex` expects a regex")?;
+                    let regex = RegexBuilder::new(&pattern).case_insensitive(true).build();
+                    config.name_regex = Some(regex.map_err(|e| format!("invalid regex given to `--name-regex`: `{pattern}`: {e}"))?)
+                }
+                "--no-std-paths" => config.no_std_paths = true,
+                "--suffix-paths" => config.suffix_paths = true,
+                "--equiv" => {
+                    let path = args.next().ok_or("`--equiv` expects a path")?;
+                    config.equiv = Some(PathBuf::from(path))
+                }
+                "--expand-aliases" => config.expand_aliases = true,
+                "--no-hidden" => config.no_hidden = true,
+                "--no-deprecated" => config.no_deprecated = true,
+                "--exclude-file" => {
+                    let path = args.next().ok_or("`--exclude-file` expects a path")?;
+                    config.exclude_files.push(PathBuf::from(path))
+                }
+                "--crate" => config.crate_name = Some(args.next().ok_or("`--crate` expects a crate name")?),
+                "--no-impl-methods" => config.no_impl_methods = true,
+                "--sort-by" => {
+                    let key = args.next().ok_or("`--sort-by` expects `path`, `name` or `kind`")?;
+                    config.sort_by = SortBy::parse(&key)?
+                }
+                "--variant" => {
+                    let name = args.next().ok_or("`--variant` expects a variant name")?;
+                    config.variant = Some(name)
+                }
+                "--variant-type" => {
+                    let ty = args.next().ok_or("`--variant-type` expects a type")?;
+                    if let Err(e) = syn::parse_str::<syn::Type>(&ty) {
+                        return Err(format!("invalid type given to `--variant-type`: `{ty}`: {e}"))
+                    }
+                    config.variant_type = Some(ty)
+                }
+                "--relative-to" => {
+                    let path = args.next().ok_or("`--relative-to` expects a directory")?;
+                    config.relative_to = Some(PathBuf::from(path))
+                }
+                "--machine-summary" => config.machine_summary = true,
+                "--constructors" => {
+                    let ty = args.next().ok_or("`--constructors` expects a type")?;
+                    config.constructors = Some(ty)
+                }
+                "--similar" => config.similar = Some(parse_location(args.next())?),
+                "-v" | "--verbose" => config.verbose = true,
+                _ if arg.starts_with("--") => return Err(format!("unknown option: `{arg}`")),
+                _ => if config.query.replace(arg).is_some() {
+                    return Err("more than one query given".to_owned())
+                }
+            }
+        }
+
+        if config.query.is_some() && config.query_file.is_some() {
+            return Err("both a query and `--query-file` given".to_owned())
+        }
+        if config.similar.is_some() && (config.query.is_some() || config.query_file.is_some()) {
+            return Err("both a query and `--similar` given".to_owned())
+        }
+        if let Some(ref ty) = config.constructors {
+            if let Err(e) = syn::parse_str::<syn::Type>(ty) {
+                return Err(format!("invalid type given to `--constructors`: `{ty}`: {e}"))
+            }
+        }
+        if config.porcelain && config.is_json() {
+            return Err("both `--porcelain` and a JSON format given".to_owned())
+        }
+        if config.porcelain && config.quickfix {
+            return Err("both `--porcelain` and `--format quickfix` given".to_owned())
+        }
+        if config.picker && (config.porcelain || config.quickfix || config.is_json()) {
+            return Err("both `--picker` and another output format given".to_owned())
+        }
+        if config.template.is_some() && (config.picker || config.porcelain || config.quickfix || config.is_json()) {
+            return Err("both `--template` and another output format given".to_owned())
+        }
+        if config.generic_only && config.non_generic_only {
+            return Err("both `--generic-only` and `--non-generic-only` given".to_owned())
+        }
+        if config.file.is_some() && config.workspace {
+            return Err("both `--file` and `--workspace` given".to_owned())
+        }
+        if config.line_range.is_some() && config.file.is_none() {
+            return Err("`--line-range` needs `--file`".to_owned())
+        }
+        if config.after.zip(config.before).is_some_and(|(after, before)| after >= before) {
+            return Err("`--after` is not before `--before`".to_owned())
+        }
+        if config.fallible && config.infallible {
+            return Err("both `--fallible` and `--infallible` given".to_owned())
+        }
+        if config.variant.is_some() && config.variant_type.is_some() {
+            return Err("both `--variant` and `--variant-type` given".to_owned())
+        }
+
+        Ok(config)
+    }
+
+    /// Checks whether `count` arguments are within `--min-args` and `--max-args`.
+    #[inline]
+    pub fn arg_count_matches(&self, count: usize) -> bool {
+        self.min_args.is_none_or(|min| count >= min) && self.max_args.is_none_or(|max| count <= max)
+    }
+
+    /// Whether an option that runs instead of a query, or without one, was given.
+    #[inline]
+    pub fn runs_without_query(&self) -> bool {
+        self.build_index ||
+            self.repl ||
+            self.types ||
+            self.top_types.is_some() ||
+            self.variant.is_some() ||
+            self.variant_type.is_some() ||
+            self.constructors.is_some() ||
+            self.similar.is_some() ||
+            self.data_variants.is_some() ||
+            self.derives.is_some() ||
+            self.name_regex.is_some()
+    }
+
+    /// Checks whether a function, generic or not, passes `--generic-only` and `--non-generic-only`.
+    #[inline]
+    pub fn genericity_matches(&self, is_generic: bool) -> bool {
+        if is_generic { !self.non_generic_only } else { !self.generic_only }
+    }
+
+    /// Checks whether a function, returning a `Result` or not, passes `--fallible` and `--infallible`.
+    #[inline]
+    pub fn fallibility_matches(&self, is_fallible: bool) -> bool {
+        if is_fallible { !self.infallible } else { !self.fallible }
+    }
+
+    /// How deeply nested types are compared at most, see `--max-type-depth`.
+    #[inline]
+    pub fn type_depth_limit(&self) -> usize {
+        self.max_type_depth.unwrap_or(32)
+    }
+
+    /// Checks whether a file modified at `mtime` passes `--after` and `--before`, a file whose
+    /// modification time can't be read only does without them.
+    #[inline]
+    pub fn mtime_matches(&self, mtime: Option::<SystemTime>) -> bool {
+        match mtime {
+            Some(mtime) => self.after.is_none_or(|after| mtime > after) && self.before.is_none_or(|before| mtime < before),
+            None => self.after.is_none() && self.before.is_none()
+        }
+    }
+
+    /// Checks whether items of `kind` pass `--kind` and `--not-kind`.
+    #[inline]
+    pub fn kind_matches(&self, kind: Kind) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&kind)) && !self.not_kinds.contains(&kind)
+    }
+
+    /// Whether the output is JSON, which nothing else may be printed to stdout along with.
+    #[inline]
+    pub fn is_json(&self) -> bool {
+        self.json || self.json_lines || self.json_full
+    }
+
+    /// Whether the output is for other programs, JSON, `--porcelain`, quickfix, `--picker` or
+    /// `--template` lines.
+    #[inline]
+    pub fn is_machine_readable(&self) -> bool {
+        self.is_json() || self.porcelain || self.quickfix || self.picker || self.template.is_some()
+    }
+
+    /// Makes `self` the global config returned by `config()`.
+    #[inline]
+    pub fn init(self) -> &'static Config {
+        CONFIG.get_or_init(|| self)
+    }
+
+    /// Returns the query text, reading it from `--query-file` if one was given, or from stdin if
+    /// that path is `-`. `//` comments are stripped from read queries.
+    pub fn read_query(&self) -> io::Result::<Option::<String>> {
+        let Some(ref path) = self.query_file else { return Ok(self.query.clone()) };
+        let query = if path.as_os_str() == "-" {
+            io::read_to_string(io::stdin())?
+        } else {
+            read_to_string(path)?
+        };
+        Ok(Some(strip_comments(&query)))
+    }
+}
+
+/// Drops the `//` comments of a query read from a file, the lines with nothing else in them
+/// included, and trims it. The rest is kept verbatim. Nothing in the query grammar can contain
+/// `//`, so everything after one is a comment.
+fn strip_comments(query: &str) -> String {
+    let query = query.split_inclusive('\n').filter_map(|line| {
+        let Some((code, _)) = line.split_once("//") else { return Some(Cow::Borrowed(line)) };
+        let ending = &line[line.trim_end_matches(['\r', '\n']).len()..];
+        let code = code.trim_end();
+        (!code.is_empty()).then(|| Cow::Owned(format!("{code}{ending}")))
+    }).collect::<String>();
+    query.trim().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::item::{Item, parse_query};
+
+    #[test]
+    fn commented_query_files() {
+        let file = "\n// Connects to a peer.\n\nfn(SocketAddr) // the address\n    -> io::Result<TcpStream>\n\n// trailing\n";
+        let query = strip_comments(file);
+        assert_eq!(query, "fn(SocketAddr)\n    -> io::Result<TcpStream>");
+        assert!(matches!(parse_query(&query).unwrap().item, Item::FnSignature(..)));
+        assert_eq!(strip_comments("struct {\r\n  a: u8, // first\r\n  b: u8\r\n}\r\n"), "struct {\r\n  a: u8,\r\n  b: u8\r\n}");
+        assert_eq!(strip_comments("// only comments\n\n"), "");
+    }
+
+    #[test]
+    fn uncommented_query_files_are_verbatim() {
+        let query = "fn(Vec < u8 >,\r\n   &str)  ->  usize";
+        assert_eq!(strip_comments(&format!("\n  {query}\n\n")), query);
+    }
+}
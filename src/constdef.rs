@@ -0,0 +1,123 @@
+use syn::{
+    Type,
+    Ident,
+    Token,
+    ItemConst,
+    ImplItemConst,
+    parse::{Parse, ParseStream}
+};
+
+use crate::loc::Loc;
+use crate::attrs::Attrs;
+use crate::{skip_tokens, to_static_str};
+use crate::normalize::{self_ty_matches, type_to_static_str};
+
+/// A `const` item, either free or associated, or a query for one. Every part of a query is
+/// optional: `const MAX: usize`, `const _: usize`, `const Duration::_` and a plain `const` all work.
+#[derive(Debug)]
+pub struct ConstDef {
+    pub name: Option::<&'static str>,
+    pub ty: Option::<&'static str>,
+    /// The self type of the `impl` block an associated constant is declared in.
+    pub self_ty: Option::<&'static str>,
+    pub attrs: Attrs
+}
+
+pub type ConstDefs<'a> = Vec::<(Loc<'a>, ConstDef)>;
+
+impl ConstDef {
+    #[inline]
+    pub fn from_impl_item(item: ImplItemConst, self_ty: &'static str) -> Self {
+        Self {
+            name: Some(to_static_str(&item.ident)),
+            ty: Some(type_to_static_str(item.ty)),
+            self_ty: Some(self_ty),
+            attrs: Attrs::from(item.attrs.as_slice())
+        }
+    }
+
+    /// Checks whether `other` matches `self` as a query. The self type of the query matches
+    /// regardless of the generics and the path prefix of the indexed one, so `Duration::_`
+    /// finds the constants of `impl std::time::Duration` and `Vec::_` the ones of `impl<T> Vec<T>`.
+    pub fn matches(&self, other: &ConstDef) -> bool {
+        self.name.is_none_or(|name| other.name == Some(name)) &&
+            self.ty.is_none_or(|ty| other.ty == Some(ty)) &&
+            self.self_ty.is_none_or(|self_ty| other.self_ty.is_some_and(|other| self_ty_matches(self_ty, other)))
+    }
+}
+
+impl From::<ItemConst> for ConstDef {
+    #[inline]
+    fn from(item: ItemConst) -> Self {
+        Self {
+            name: Some(to_static_str(&item.ident)),
+            ty: Some(type_to_static_str(*item.ty)),
+            self_ty: None,
+            attrs: Attrs::from(item.attrs.as_slice())
+        }
+    }
+}
+
+impl Parse for ConstDef {
+    fn parse(input: ParseStream) -> syn::Result::<Self> {
+        skip_tokens!(input, const);
+
+        // `Self::Type::NAME`, where any segment can be `_`.
+        let mut path = Vec::new();
+        while input.peek(Ident) || input.peek(Token![_]) {
+            if input.parse::<Token![_]>().is_ok() {
+                path.push(None)
+            } else {
+                path.push(Some(to_static_str(&input.parse::<Ident>()?)))
+            }
+            if input.parse::<Token![::]>().is_err() { break }
+        }
+        let name = path.pop().flatten();
+        let self_ty = if path.is_empty() || path.iter().any(Option::is_none) {
+            None
+        } else {
+            let self_ty = path.into_iter().flatten().collect::<Vec::<_>>().join("::");
+            Some(&*Box::leak(self_ty.into_boxed_str()))
+        };
+
+        let ty = if input.parse::<Token![:]>().is_ok() {
+            match input.parse::<Type>()? {
+                Type::Infer(..) => None,
+                ty => Some(type_to_static_str(ty))
+            }
+        } else {
+            None
+        };
+
+        Ok(ConstDef {name, ty, self_ty, attrs: Attrs::default()})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::Index;
+    use crate::item::{Item, parse_query};
+
+    /// The names of the constants of `code` the const query `query` matches, with their self types.
+    fn found(query: &str, code: &str) -> Vec::<(Option::<&'static str>, &'static str)> {
+        let Item::ConstDef(query) = parse_query(query).unwrap().item else { panic!("expected a const query") };
+        let index = Index::from_sources([("a.rs".into(), code.to_owned())]);
+        index.files[0].3.iter()
+            .filter(|(_, def)| query.matches(def))
+            .map(|(_, def)| (def.self_ty, def.name.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn associated_consts() {
+        let code = "
+            const LIMIT: usize = 1;
+            impl std::time::Duration { const MAX: usize = 100; fn f() {} }
+            impl<T> Vec<T> { const EMPTY: usize = 0; }
+        ";
+        assert_eq!(found("const Duration::_", code), [(Some("duration"), "max")]);
+        assert_eq!(found("const Vec::EMPTY", code), [(Some("vec<t>"), "empty")]);
+        assert_eq!(found("const _: usize", code), [(None, "limit"), (Some("duration"), "max"), (Some("vec<t>"), "empty")]);
+        assert_eq!(found("const Duration::_: u8", code), []);
+    }
+}
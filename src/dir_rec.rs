@@ -22,17 +22,50 @@
 // WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
 use std::fs::read_dir;
+use std::ffi::OsString;
 use std::path::PathBuf;
 use std::collections::VecDeque;
 
 pub struct DirRec {
-    stack: VecDeque::<PathBuf>
+    stack: VecDeque::<PathBuf>,
+    skip_dirs: Vec::<OsString>,
+    hidden: bool
 }
 
 impl DirRec {
     #[inline(always)]
     pub fn new<P: Into::<PathBuf>>(root: P) -> DirRec {
-        DirRec {stack: vec![root.into()].into()}
+        DirRec {stack: vec![root.into()].into(), skip_dirs: Vec::new(), hidden: false}
+    }
+
+    /// Walks every one of `roots`, in order.
+    #[inline(always)]
+    pub fn with_roots<I, P>(roots: I) -> DirRec
+    where
+        I: IntoIterator<Item = P>,
+        P: Into::<PathBuf>
+    {
+        DirRec {stack: roots.into_iter().map(Into::into).collect(), skip_dirs: Vec::new(), hidden: false}
+    }
+
+    /// Walks the files and directories whose name starts with a `.` too, like `.cargo`, which are
+    /// skipped by default. The root is walked either way.
+    #[inline(always)]
+    pub fn hidden(mut self, hidden: bool) -> DirRec {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Prunes the directories named one of `names` from the walk, compared by their last path
+    /// component only, so they aren't descended into at all. The root is never pruned.
+    #[inline(always)]
+    pub fn skip_dirs<I, S>(mut self, names: I) -> DirRec
+    where
+        I: IntoIterator<Item = S>,
+        S: Into::<OsString>
+    {
+        self.skip_dirs.extend(names.into_iter().map(Into::into));
+        self
     }
 }
 
@@ -43,7 +76,13 @@ impl Iterator for DirRec {
         while let Some(p) = self.stack.pop_front() {
             if p.is_file() { return Some(p) }
             let Ok(es) = read_dir(&p) else { continue };
-            es.filter_map(Result::ok).for_each(|e| {
+            es.filter_map(Result::ok).filter(|e| {
+                self.hidden || !e.file_name().as_encoded_bytes().starts_with(b".")
+            }).filter(|e| {
+                self.skip_dirs.is_empty() ||
+                    !e.file_type().is_ok_and(|t| t.is_dir()) ||
+                    !self.skip_dirs.contains(&e.file_name())
+            }).for_each(|e| {
                 self.stack.push_back(e.path())
             });
         } None
@@ -1,5 +1,9 @@
+use std::mem::discriminant;
+use std::collections::HashSet;
+
 use rayon::prelude::*;
 use syn::{
+    Expr,
     Type,
     Ident,
     Token,
@@ -11,10 +15,12 @@ use syn::{
     }
 };
 
-use crate::{loc::Loc, print_results};
+use crate::{loc::Loc, Results};
+use crate::attrs::{Attrs, derives};
 use crate::fields::*;
 use crate::enummap::*;
 use crate::{skip_tokens, to_static_str};
+use crate::normalize::{bound_traits, type_to_static_str};
 
 #[derive(Debug)]
 pub struct Variant {
@@ -25,12 +31,40 @@ pub struct Variant {
 #[derive(Debug)]
 pub struct EnumDef {
     pub name: Option::<&'static str>,
-    pub variants: Vec::<Variant>
+    pub variants: Vec::<Variant>,
+    /// The traits the type parameters are bounded by, see `bound_traits`.
+    pub bounds: Vec::<&'static str>,
+    /// The traits it derives, see `derives`.
+    pub derives: Vec::<&'static str>,
+    pub attrs: Attrs
 }
 
 impl EnumDef {
-    pub fn search_enum_def<'a>(query: &'a EnumDef, enums: &'a EnumDefs) {
-        let cache = EnumMap::new(enums);
+    /// Number of variants carrying data, in named or unnamed fields, which is 0 for C-like enums.
+    #[inline]
+    pub fn data_variants(&self) -> usize {
+        self.variants.iter().filter(|v| !matches!(v.fields, Fields::Unit)).count()
+    }
+
+    /// Checks whether `self` has every variant `query` names, of the same kind, tuple, struct or
+    /// unit, with the fields of the query's, for `--strict-variants`. The fields of a query
+    /// variant without a name, like the `Duration` of `enum { Duration }`, can be in any variant.
+    pub fn has_variants_of(&self, query: &EnumDef) -> bool {
+        let has_fields = |variant: &Variant, fields: &Fields| fields.iter().all(|qf| variant.fields.iter().any(|f| qf.matches(f)));
+        query.variants.iter().all(|qv| match qv.name {
+            Some(name) => self.variants.iter().any(|v| {
+                v.name == Some(name) && discriminant(&v.fields) == discriminant(&qv.fields) && has_fields(v, &qv.fields)
+            }),
+            None => qv.fields.iter().all(|qf| self.variants.iter().flat_map(|v| v.fields.iter()).any(|f| qf.matches(f)))
+        })
+    }
+
+    /// Finds the enums having any of the names or field types of `query`. Field types are looked
+    /// up whatever the kind of their variant, so `enum { V(Duration) }` finds a `V { t: Duration }`
+    /// payload as well as a `V(Duration)` one, unless `--strict-variants` is set, see
+    /// `has_variants_of`.
+    pub fn search_enum_def<'a>(query: &EnumDef, enums: &[&'a (Loc<'a>, EnumDef)]) -> Results<'a, 'a> {
+        let cache = EnumMap::new(enums.iter().copied());
 
         let mut vnames = Vec::new();
         let mut name_candidates = query.variants
@@ -61,26 +95,30 @@ impl EnumDef {
             .flatten()
             .collect::<Vec<_>>();
 
-        let results = name_candidates
+        // An enum found by several names or types of the query is reported once.
+        let mut seen = HashSet::new();
+        name_candidates
             .into_iter()
             .chain(type_candidates)
-            .map(std::ops::Deref::deref)
-            .collect();
-
-        print_results(&results);
+            .copied()
+            .filter(|loc| seen.insert(*loc))
+            .collect()
     }
 }
 
 impl From::<syn::ItemEnum> for EnumDef {
     fn from(e: syn::ItemEnum) -> Self {
         let name = Some(to_static_str(&e.ident));
+        let attrs = Attrs::from(e.attrs.as_slice());
+        let derives = derives(&e.attrs);
+        let bounds = bound_traits(&e.generics);
         let variants = e.variants.into_iter().map(|v| {
             Variant {
                 name: Some(to_static_str(&v.ident)),
                 fields: Fields::from(v.fields),
             }
         }).collect();
-        Self {name, variants}
+        Self {name, variants, bounds, derives, attrs}
     }
 }
 
@@ -101,8 +139,15 @@ impl Parse for EnumDef {
         while !content.is_empty() {
             let name = {
                 let name = content.parse::<Type>().as_ref().map(to_static_str).ok();
-                skip_tokens!(content, ,);
-                if content.is_empty() {
+                // A discriminant, like the `= 1` of `Red = 1`, is skipped: C-like enums match by
+                // their variant names whatever their values.
+                if content.peek(Token![=]) {
+                    content.parse::<Token![=]>()?;
+                    content.parse::<Expr>()?;
+                }
+                let comma = content.parse::<Option::<Token![,]>>()?.is_some();
+                if content.is_empty() && variants.is_empty() && !comma {
+                    // A query of a single word, like `enum { Duration }`, is one of a field type.
                     variants.push(Variant {
                         name: None,
                         fields: Fields::Unnamed(vec![Field {
@@ -111,6 +156,9 @@ impl Parse for EnumDef {
                         }])
                     });
                     break
+                } else if comma || content.is_empty() {
+                    variants.push(Variant {name, fields: Fields::Unit});
+                    continue
                 } else {
                     name
                 }
@@ -137,7 +185,7 @@ impl Parse for EnumDef {
                     syn::parenthesized!(inner_content in content);
                     let mut fields = Vec::new();
                     while !inner_content.is_empty() {
-                        let ty = Some(to_static_str(&inner_content.parse::<Type>().unwrap()));
+                        let ty = Some(type_to_static_str(inner_content.parse::<Type>().unwrap()));
                         fields.push(Field { name: None, ty });
                         if inner_content.is_empty() { break; }
                         inner_content.parse::<Token![,]>().unwrap();
@@ -147,7 +195,7 @@ impl Parse for EnumDef {
             } else {
                 Fields::Unnamed(vec![Field {
                     name: None,
-                    ty: Some(to_static_str(&content.parse::<Type>().unwrap()))
+                    ty: Some(type_to_static_str(content.parse::<Type>().unwrap()))
                 }])
             };
 
@@ -158,8 +206,63 @@ impl Parse for EnumDef {
             }
         }
 
-        Ok(EnumDef { name, variants })
+        Ok(EnumDef { name, variants, bounds: Vec::new(), derives: Vec::new(), attrs: Attrs::default() })
     }
 }
 
 pub type EnumDefs<'a> = Vec::<(Loc<'a>, EnumDef)>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::index::Index;
+
+    const CODE: &str = "\
+enum Timer { V(Duration), Stop }
+enum Named { V { t: Duration }, Stop }
+enum Other { W(Duration) }
+";
+
+    fn query(query: &str) -> EnumDef {
+        syn::parse_str(query).unwrap()
+    }
+
+    /// The names of the enums of `code` the enum query `query` finds, sorted, only the ones having
+    /// its variants with `strict`, like `--strict-variants`.
+    fn found(query: &str, code: &str, strict: bool) -> Vec::<&'static str> {
+        let query = self::query(query);
+        let index = Index::from_sources([("a.rs".into(), code.to_owned())]);
+        let enums = index.files[0].2.iter().collect::<Vec::<_>>();
+        let mut names = EnumDef::search_enum_def(&query, &enums).into_iter()
+            .filter_map(|loc| enums.iter().find(|(l, _)| l == loc).map(|(_, def)| def))
+            .filter(|def| !strict || def.has_variants_of(&query))
+            .filter_map(|def| def.name)
+            .collect::<Vec::<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn payload_types_match_across_variant_kinds() {
+        assert_eq!(found("enum { V(Duration) }", CODE, false), ["named", "other", "timer"]);
+        assert_eq!(found("enum { Duration }", CODE, false), ["named", "other", "timer"]);
+    }
+
+    #[test]
+    fn strict_variants() {
+        assert_eq!(found("enum { V(Duration) }", CODE, true), ["timer"]);
+        assert_eq!(found("enum { V { t: Duration } }", CODE, true), ["named"]);
+        assert_eq!(found("enum { V { t: _ }, Stop }", CODE, true), ["named"]);
+        assert_eq!(found("enum { Stop(u8) }", CODE, true), Vec::<&str>::new());
+        assert_eq!(found("enum { Duration }", CODE, true), ["named", "other", "timer"]);
+    }
+
+    #[test]
+    fn c_like_enums() {
+        let code = "enum Color { Red = 1, Green = 2, Blue = 4 }\nenum Light { Red, Green }\nenum Paint { Red(u8), Green, Blue }";
+        assert_eq!(found("enum { Red, Green, Blue }", code, true), ["color"]);
+        assert_eq!(found("enum { Red = 1, Green = 2, Blue = 4 }", code, true), ["color"]);
+        assert_eq!(found("enum { Red = 8, Green }", code, true), ["color", "light"]);
+    }
+}
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use std::hash::BuildHasherDefault;
 
 use crate::Results;
+use crate::loc::Loc;
 use crate::enumdef::*;
 
 type FastHashMap<K, V> = HashMap<K, V, BuildHasherDefault::<XxHash64>>;
@@ -10,20 +11,24 @@ type FastHashMap<K, V> = HashMap<K, V, BuildHasherDefault::<XxHash64>>;
 pub struct EnumMap<'a> {
     pub name_map: FastHashMap::<&'static str, Results<'a, 'a>>,
     pub type_map: FastHashMap::<&'static str, Results<'a, 'a>>,
+    /// Variant names only, unlike `name_map` which also has enum and field names.
+    pub variant_map: FastHashMap::<&'static str, Results<'a, 'a>>,
 }
 
 impl<'a> EnumMap<'a> {
-    pub fn new(enums: &'a EnumDefs) -> Self {
+    pub fn new(enums: impl IntoIterator<Item = &'a (Loc<'a>, EnumDef)>) -> Self {
         let mut name_map = FastHashMap::<&'static str, Results<'a, 'a>>::default();
         let mut type_map = FastHashMap::<&'static str, Results<'a, 'a>>::default();
+        let mut variant_map = FastHashMap::<&'static str, Results<'a, 'a>>::default();
 
-        enums.iter().for_each(|(loc, enum_def)| {
+        enums.into_iter().for_each(|(loc, enum_def)| {
             if let Some(name) = &enum_def.name {
                 name_map.entry(name).or_default().push(loc);
             }
             for variant in &enum_def.variants {
                 if let Some(name) = &variant.name {
                     name_map.entry(name).or_default().push(loc);
+                    variant_map.entry(name).or_default().push(loc);
                 }
                 for f in variant.fields.iter() {
                     if let Some(name) = &f.name {
@@ -36,6 +41,6 @@ impl<'a> EnumMap<'a> {
             }
         });
 
-        Self { name_map, type_map }
+        Self { name_map, type_map, variant_map }
     }
 }
@@ -0,0 +1,378 @@
+//! The type equivalences of `--equiv`, which make types roogle can't tell are the same, like a
+//! domain alias and the type it stands for, interchangeable in both the index and the queries.
+//! An alias with type parameters, like `Map<K> = HashMap<K, Vec<u8>>`, is expanded with the type
+//! arguments it's given instead, so `Map<String>` is `HashMap<String, Vec<u8>>`.
+//!
+//! With `--expand-aliases`, the `type` aliases of the searched source files are expanded the same
+//! way, see `set_source_aliases`.
+
+use std::fs::read_to_string;
+use std::path::Path;
+use std::cell::Cell;
+use std::sync::{OnceLock, RwLock};
+use std::collections::{HashMap, HashSet};
+
+use quote::ToTokens;
+use syn::{Type, Token, ItemType, GenericArgument, PathArguments, parse::{Parse, ParseStream}};
+
+use crate::config::config;
+use crate::normalize::{normalize, canonicalize, type_to_string};
+
+/// Aliases by their path, with their type parameters and the type they stand for, normalized.
+type Aliases = HashMap::<String, (Vec::<String>, String)>;
+
+#[derive(Default)]
+struct Equivs {
+    /// Every type of an equivalence class, normalized, mapped to the representative of its class.
+    types: HashMap::<String, String>,
+    /// The aliases with type parameters.
+    aliases: Aliases
+}
+
+static EQUIVS: OnceLock::<Equivs> = OnceLock::new();
+
+/// The aliases of the searched source files, replaced whenever the REPL indexes them again.
+static SOURCE_ALIASES: RwLock::<Option::<&'static Aliases>> = RwLock::new(None);
+
+/// How deep aliases can be expanded within each other, see `substitute`.
+const MAX_EXPANSION_DEPTH: usize = 16;
+
+thread_local! {
+    /// How many aliases are being expanded within each other on this thread.
+    static EXPANSION_DEPTH: Cell::<usize> = const { Cell::new(0) };
+}
+
+#[cfg(test)]
+thread_local! {
+    /// The equivalences of the test running on this thread, see `with_equivs`.
+    static TEST_EQUIVS: Cell::<Option::<&'static Equivs>> = const { Cell::new(None) };
+    /// The source aliases of the test running on this thread, see `set_source_aliases`.
+    static TEST_SOURCE_ALIASES: Cell::<Option::<&'static Aliases>> = const { Cell::new(None) };
+}
+
+/// The equivalences `load` read, if it was called.
+#[inline]
+fn equivs() -> Option::<&'static Equivs> {
+    #[cfg(test)]
+    if let Some(equivs) = TEST_EQUIVS.get() { return Some(equivs) }
+    EQUIVS.get()
+}
+
+/// The source aliases `set_source_aliases` was last given, if any and `--expand-aliases` is set.
+#[inline]
+fn source_aliases() -> Option::<&'static Aliases> {
+    if !config().expand_aliases { return None }
+    #[cfg(test)]
+    if let Some(aliases) = TEST_SOURCE_ALIASES.get() { return Some(aliases) }
+    SOURCE_ALIASES.read().map_or(None, |aliases| *aliases)
+}
+
+/// Makes the type aliases `items` of the searched source files apply to every type normalized
+/// from then on, looked up by their name, for the paths of one segment or starting with `crate`,
+/// `self` or `super`. Aliases of the same name standing for different types are left out, as it
+/// can't be told which one a path means. In tests, only the running test sees them.
+pub fn set_source_aliases<'a>(items: impl IntoIterator<Item = &'a ItemType>) {
+    // The aliases given before must not apply to the types of the new ones.
+    #[cfg(test)]
+    TEST_SOURCE_ALIASES.set(None);
+    #[cfg(not(test))]
+    if let Ok(mut aliases) = SOURCE_ALIASES.write() { *aliases = None }
+
+    let mut found = HashMap::<String, Option::<(Vec::<String>, String)>>::new();
+    items.into_iter().for_each(|item| {
+        let params = item.generics.type_params().map(|param| param.ident.to_string().to_lowercase()).collect();
+        let Ok(ty) = syn::parse2::<Type>(item.ty.to_token_stream()) else { return };
+        let alias = (params, type_to_string(ty));
+        let entry = found.entry(item.ident.to_string().to_lowercase()).or_insert_with(|| Some(alias.clone()));
+        if entry.as_ref().is_some_and(|found| *found != alias) { *entry = None }
+    });
+    let aliases = found.into_iter().filter_map(|(name, alias)| Some((name, alias?))).collect::<Aliases>();
+    let aliases = (!aliases.is_empty()).then(|| &*Box::leak(Box::new(aliases)));
+
+    #[cfg(test)]
+    TEST_SOURCE_ALIASES.set(aliases);
+    #[cfg(not(test))]
+    if let Ok(mut source_aliases) = SOURCE_ALIASES.write() { *source_aliases = aliases }
+}
+
+/// The source alias the path `path` names, if it's one `source_aliases` can tell.
+fn source_alias<'a>(aliases: &'a Aliases, path: &str) -> Option::<&'a (Vec::<String>, String)> {
+    let mut segments = path.rsplit("::");
+    let name = segments.next()?;
+    if segments.any(|segment| !matches!(segment, "crate" | "self" | "super")) { return None }
+    aliases.get(name)
+}
+
+/// Runs `f` with the equivalences of the equivalence file `file` applying on this thread, like
+/// `config::with_config` does for options.
+#[cfg(test)]
+pub fn with_equivs<T>(file: &str, f: impl FnOnce() -> T) -> T {
+    let equivs = parse(file, Path::new("equivs.txt")).unwrap();
+    let previous = TEST_EQUIVS.replace(Some(Box::leak(Box::new(equivs))));
+    let result = f();
+    TEST_EQUIVS.set(previous);
+    result
+}
+
+/// One `Bytes = Vec<u8>` line of an equivalence file.
+struct Equiv(Type, Type);
+
+impl Parse for Equiv {
+    fn parse(input: ParseStream) -> syn::Result::<Self> {
+        let ty = input.parse()?;
+        input.parse::<Token![=]>()?;
+        Ok(Equiv(ty, input.parse()?))
+    }
+}
+
+/// Reads the equivalence file at `path`, one `A = B` line per equivalence, `#` starting comments,
+/// and makes its equivalences apply to every type normalized from then on. Equivalences chain, so
+/// `A = B` and `B = C` make all three interchangeable, each class of types standing for the first
+/// one the file gives. A type can't be equivalent to a type it's part of, like `A = Vec<A>`.
+/// A side whose type arguments are all single letters, like `Map<K>`, is a generic alias of the
+/// other side, the letters being its parameters, see `generic_alias`.
+pub fn load(path: &Path) -> Result::<(), String> {
+    let file = read_to_string(path).map_err(|e| format!("could not read `{path}`: {e}", path = path.display()))?;
+    _ = EQUIVS.set(parse(&file, path)?);
+    Ok(())
+}
+
+/// Parses the equivalence file `file` read from `path`, see `load`.
+fn parse(file: &str, path: &Path) -> Result::<Equivs, String> {
+    // The types of each class, the first one being its representative.
+    let mut classes = Vec::<Vec::<String>>::new();
+    let mut aliases = HashMap::new();
+    for (i, line) in file.lines().enumerate() {
+        let line = line.split_once('#').map_or(line, |(equiv, _)| equiv).trim();
+        if line.is_empty() { continue }
+        let Equiv(a, b) = syn::parse_str::<Equiv>(line).map_err(|e| {
+            format!("{path}:{line}: expected `Type = Type`: {e}", path = path.display(), line = i + 1)
+        })?;
+        // A generic alias stands for the other side instead of being in a class.
+        let alias = match (generic_alias(&a), generic_alias(&b)) {
+            (Some(alias), _) => Ok((alias, b)),
+            (None, Some(alias)) => Ok((alias, a)),
+            (None, None) => Err((a, b))
+        };
+        let (a, b) = match alias {
+            Ok(((name, params), ty)) => {
+                aliases.insert(name, (params, type_to_string(ty)));
+                continue
+            }
+            Err(equiv) => equiv
+        };
+        // Normalized before `EQUIVS` is set, so without the equivalences themselves.
+        let (a, b) = (type_to_string(a), type_to_string(b));
+        if contains_type(&a, &b) || contains_type(&b, &a) {
+            return Err(format!("{path}:{line}: `{a}` = `{b}` is cyclic", path = path.display(), line = i + 1))
+        }
+        let class_of = |ty: &str, classes: &[Vec::<String>]| classes.iter().position(|class| class.iter().any(|t| t == ty));
+        match (class_of(&a, &classes), class_of(&b, &classes)) {
+            (Some(i), Some(j)) if i == j => {}
+            (Some(i), Some(j)) => {
+                let merged = classes.remove(i.max(j));
+                classes[i.min(j)].extend(merged)
+            }
+            (Some(i), None) => classes[i].push(b),
+            (None, Some(j)) => classes[j].push(a),
+            (None, None) => classes.push(vec![a, b])
+        }
+    }
+    if let Some(name) = cyclic_alias(&aliases) {
+        return Err(format!("{path}: `{name}` stands for a type it's part of", path = path.display()))
+    }
+    let types = classes.into_iter().flat_map(|class| {
+        let representative = class[0].clone();
+        class.into_iter().map(move |ty| (ty, representative.clone()))
+    }).collect();
+    Ok(Equivs {types, aliases})
+}
+
+/// The path of `ty` and its type arguments, as `type_to_string` renders them, its lifetimes left
+/// out. A path without arguments has none.
+fn path_and_args(ty: &Type) -> Option::<(String, Vec::<&Type>)> {
+    let Type::Path(p) = ty else { return None };
+    if p.qself.is_some() { return None }
+    let args = match p.path.segments.last()?.arguments {
+        PathArguments::None => Vec::new(),
+        PathArguments::AngleBracketed(ref args) => args.args.iter().filter_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(Some(ty)),
+            GenericArgument::Lifetime(..) => None,
+            _ => Some(None)
+        }).collect::<Option::<Vec::<_>>>()?,
+        PathArguments::Parenthesized(..) => return None
+    };
+    let path = p.path.segments.iter().map(|seg| seg.ident.to_string().to_lowercase()).collect::<Vec::<_>>().join("::");
+    Some((path, args))
+}
+
+/// The path and the parameters of `ty` if it's a generic alias, like `Map<K>`: a path whose type
+/// arguments are all distinct single letters, like the type variables of queries.
+fn generic_alias(ty: &Type) -> Option::<(String, Vec::<String>)> {
+    let (path, args) = path_and_args(ty).filter(|(_, args)| !args.is_empty())?;
+    let mut params = Vec::<String>::new();
+    for arg in args {
+        let Type::Path(p) = arg else { return None };
+        let param = p.path.get_ident()?.to_string().to_lowercase();
+        if param.len() != 1 || !param.as_bytes()[0].is_ascii_alphabetic() || params.contains(&param) { return None }
+        params.push(param)
+    }
+    Some((path, params))
+}
+
+/// The first alias found standing for a type it's part of, directly or through other aliases.
+fn cyclic_alias<'a>(aliases: &'a HashMap::<String, (Vec::<String>, String)>) -> Option::<&'a str> {
+    let aliases_in = |ty: &'a str| -> Vec::<&'a str> {
+        ty.split(|c: char| !c.is_alphanumeric() && c != '_' && c != ':').filter(|word| aliases.contains_key(*word)).collect()
+    };
+    aliases.iter().find(|(name, (_, ty))| {
+        let mut seen = HashSet::new();
+        let mut next = aliases_in(ty);
+        while let Some(alias) = next.pop() {
+            if alias == name.as_str() { return true }
+            if seen.insert(alias) { next.extend(aliases_in(&aliases[alias].1)) }
+        }
+        false
+    }).map(|(name, _)| name.as_str())
+}
+
+/// Replaces the parameters `params` of an alias in the type string `ty` by their `args`.
+fn expand(ty: &str, params: &[String], args: &[String]) -> String {
+    let is_path_byte = |b: Option::<&u8>| b.is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'_' || *b == b':' || *b == b'\'');
+    let bytes = ty.as_bytes();
+    let mut expanded = String::with_capacity(ty.len());
+    ty.char_indices().for_each(|(i, c)| {
+        let param = params.iter().position(|param| {
+            ty[i..].starts_with(param.as_str()) &&
+                !is_path_byte(bytes.get(i.wrapping_sub(1))) && !is_path_byte(bytes.get(i + param.len()))
+        });
+        match param {
+            Some(param) => expanded.push_str(&args[param]),
+            None => expanded.push(c)
+        }
+    });
+    expanded
+}
+
+/// Replaces the normalized `ty` by the representative of its equivalence class, if it's in one,
+/// or by the type it stands for if it's a generic alias, or a source alias, given as many type
+/// arguments as it has parameters. Only called on types whose inner types were substituted
+/// already, and the representative isn't substituted again, so chains of equivalences can't loop.
+/// The expanded alias is, for the aliases within it, up to `MAX_EXPANSION_DEPTH` deep, as source
+/// aliases aren't checked for cycles like those of the equivalence file.
+pub fn substitute(ty: Type) -> Type {
+    let equivs = equivs().filter(|equivs| !equivs.types.is_empty() || !equivs.aliases.is_empty());
+    let source_aliases = source_aliases();
+    if equivs.is_none() && source_aliases.is_none() { return ty }
+    if let Some((path, args)) = path_and_args(&ty) {
+        let alias = equivs.and_then(|equivs| equivs.aliases.get(&path))
+            .or_else(|| source_alias(source_aliases?, &path))
+            .filter(|(params, _)| params.len() == args.len());
+        if let Some((params, aliased)) = alias.filter(|_| EXPANSION_DEPTH.get() < MAX_EXPANSION_DEPTH) {
+            let args = args.into_iter().map(|arg| canonicalize(&arg.to_token_stream().to_string().to_lowercase())).collect::<Vec::<_>>();
+            // Normalized again, for the equivalences of the types it has in it.
+            EXPANSION_DEPTH.set(EXPANSION_DEPTH.get() + 1);
+            let expanded = syn::parse_str(&expand(aliased, params, &args)).map(normalize);
+            EXPANSION_DEPTH.set(EXPANSION_DEPTH.get() - 1);
+            return expanded.unwrap_or(ty)
+        }
+    }
+    let Some(equivs) = equivs else { return ty };
+    let key = canonicalize(&ty.to_token_stream().to_string().to_lowercase());
+    match equivs.types.get(&key).filter(|representative| **representative != key) {
+        Some(representative) => syn::parse_str(representative).unwrap_or(ty),
+        None => ty
+    }
+}
+
+/// Whether the type string `part` is a whole type within `ty`, other than `ty` itself.
+fn contains_type(ty: &str, part: &str) -> bool {
+    let is_word = |b: Option::<&u8>| b.is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'_');
+    ty != part && ty.match_indices(part).any(|(i, _)| {
+        !is_word(ty.as_bytes().get(i.wrapping_sub(1))) && !is_word(ty.as_bytes().get(i + part.len()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::index::Index;
+    use crate::item::{Item, parse_query};
+
+    const ALIASES: &str = "Map<K> = HashMap<K, Vec<u8>>  # a generic alias\nBytes = Vec<u8>";
+
+    fn normalized(ty: &str) -> String {
+        type_to_string(syn::parse_str(ty).unwrap())
+    }
+
+    #[test]
+    fn generic_alias_in_return_type() {
+        with_equivs(ALIASES, || {
+            assert_eq!(normalized("Map<String>"), normalized("HashMap<String, Vec<u8>>"));
+            let index = Index::from_sources([("a.rs".into(), "fn load() -> Map<String> { todo!() }".to_owned())]);
+            let sig = &index.files[0].0[0].1;
+            assert_eq!(sig.output().to_canonical_string(), normalized("HashMap<String, Vec<u8>>"));
+            let Item::FnSignature(query) = parse_query("fn() -> HashMap<String, Bytes>").unwrap().item else { unreachable!() };
+            assert!(query.matches(sig));
+        })
+    }
+
+    #[test]
+    fn arity_mismatch_is_not_expanded() {
+        with_equivs(ALIASES, || {
+            assert_eq!(normalized("Map<String, u8>"), "map<string,u8>");
+            assert_eq!(normalized("Map"), "map");
+        })
+    }
+
+    #[test]
+    fn source_aliases() {
+        let code = "
+            type Map<K> = HashMap<K, Vec<u8>>;
+            type Bytes = Vec<u8>;
+            type Id = u64;
+            fn load(id: crate::Id) -> Map<String> { todo!() }
+            fn read() -> Bytes { todo!() }
+            fn get(id: other::Id) {}
+        ";
+        let found = |query: &str| {
+            let Item::FnSignature(query) = parse_query(query).unwrap().item else { unreachable!() };
+            let index = Index::from_sources([("a.rs".into(), code.to_owned())]);
+            index.files[0].0.iter().filter(|(_, sig)| query.matches(sig)).filter_map(|(_, sig)| sig.name().map(str::to_owned)).collect::<Vec::<_>>()
+        };
+        crate::config::with_config(&["--expand-aliases"], || {
+            assert_eq!(found("fn(u64) -> HashMap<String, Vec<u8>>"), ["load"]);
+            assert_eq!(found("fn() -> Vec<u8>"), ["read"]);
+            // Only the paths that can name the alias of the crate are expanded.
+            assert_eq!(found("fn(u64)"), Vec::<&str>::new());
+        });
+        assert_eq!(found("fn() -> Vec<u8>"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn ambiguous_and_cyclic_source_aliases() {
+        let index = |code: &str| Index::from_sources([("a.rs".into(), code.to_owned())]);
+        crate::config::with_config(&["--expand-aliases"], || {
+            index("type Id = u64;");
+            assert_eq!(normalized("Id"), "u64");
+            // Two files with aliases of the same name and different types.
+            Index::from_sources([("a.rs".into(), "type Id = u64;".to_owned()), ("b.rs".into(), "type Id = u32;".to_owned())]);
+            assert_eq!(normalized("Id"), "id");
+            // Not valid Rust, but nothing keeps a source from having it.
+            index("type List<T> = Vec<List<T>>;");
+            assert!(normalized("List<u8>").starts_with("vec<vec<"));
+        })
+    }
+
+    #[test]
+    fn cyclic_aliases() {
+        let path = Path::new("equivs.txt");
+        let e = parse("A<T> = Vec<B<T>>\nB<T> = Option<A<T>>", path).err().unwrap();
+        assert!(e.ends_with("stands for a type it's part of"), "{e}");
+        let e = parse("Tree<T> = Vec<Tree<T>>", path).err().unwrap();
+        assert_eq!(e, "equivs.txt: `tree` stands for a type it's part of");
+        let e = parse("Id = u64\nId = Vec<Id>", path).err().unwrap();
+        assert_eq!(e, "equivs.txt:2: `id` = `vec<id>` is cyclic");
+    }
+}
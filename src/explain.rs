@@ -0,0 +1,114 @@
+//! Tells why matched items matched the query, for `--explain`. Every function assumes the item
+//! does match, and names what of it the query asked for.
+
+use crate::config::config;
+use crate::enumdef::EnumDef;
+use crate::constdef::ConstDef;
+use crate::structdef::StructDef;
+use crate::traitdef::TraitDef;
+use crate::macrodef::MacroDef;
+use crate::typedef::TypeDef;
+use crate::fnsig::FnSignature;
+use crate::implquery::ImplQuery;
+use crate::highlight::render_field;
+
+/// The reason of a match that was not found by the query itself.
+pub const INVERTED: &str = "does not match the query";
+
+#[inline(always)]
+fn join(reasons: Vec::<String>, otherwise: &str) -> String {
+    if reasons.is_empty() { otherwise.to_owned() } else { reasons.join(", ") }
+}
+
+#[inline]
+pub fn fn_signature(query: &FnSignature, sig: &FnSignature) -> String {
+    query.explain(sig)
+}
+
+/// The fields of `def` that the fields of `query` matched.
+pub fn struct_def(query: &StructDef, def: &StructDef) -> String {
+    let fields = query.fields.iter()
+        .filter_map(|qf| def.fields.iter().find(|f| qf.matches(f)))
+        .map(|f| format!("`{}`", render_field(f)))
+        .collect::<Vec::<_>>();
+    if fields.is_empty() { return "any struct of that shape".to_owned() }
+    let order = if config().ordered_fields { " in that order" } else { "" };
+    format!("has {fields}{order}", fields = fields.join(", "))
+}
+
+/// The name, variants and fields `def` has in common with `query`, which is enough for an enum to
+/// match.
+pub fn enum_def(query: &EnumDef, def: &EnumDef) -> String {
+    let mut reasons = Vec::new();
+    if let Some(name) = query.name.filter(|name| def.name == Some(name)) {
+        reasons.push(format!("named `{name}`"))
+    }
+    query.variants.iter().filter_map(|v| v.name).for_each(|name| {
+        if def.variants.iter().any(|v| v.name == Some(name)) { reasons.push(format!("has the variant `{name}`")) }
+    });
+    let fields = || def.variants.iter().flat_map(|v| v.fields.iter());
+    query.variants.iter().flat_map(|v| v.fields.iter()).for_each(|qf| {
+        if let Some(name) = qf.name.filter(|name| fields().any(|f| f.name == Some(name))) {
+            reasons.push(format!("has a field named `{name}`"))
+        }
+        if let Some(ty) = qf.ty.filter(|ty| fields().any(|f| f.ty == Some(ty))) {
+            reasons.push(format!("has a field of type `{ty}`"))
+        }
+    });
+    join(reasons, "any enum")
+}
+
+pub fn const_def(query: &ConstDef) -> String {
+    let mut reasons = Vec::new();
+    if let Some(self_ty) = query.self_ty { reasons.push(format!("associated to `{self_ty}`")) }
+    if let Some(name) = query.name { reasons.push(format!("named `{name}`")) }
+    if let Some(ty) = query.ty { reasons.push(format!("of type `{ty}`")) }
+    join(reasons, "any constant")
+}
+
+pub fn trait_def(query: &TraitDef) -> String {
+    let mut reasons = Vec::new();
+    if let Some(name) = query.name { reasons.push(format!("named `{name}`")) }
+    query.assoc_types.iter().for_each(|ty| match (ty.name, ty.default) {
+        (Some(name), Some(default)) => reasons.push(format!("declares `type {name} = {default}`")),
+        (Some(name), None) => reasons.push(format!("declares `type {name}`")),
+        (None, Some(default)) => reasons.push(format!("declares a type defaulting to `{default}`")),
+        (None, None) => reasons.push("declares an associated type".to_owned())
+    });
+    join(reasons, "any trait")
+}
+
+pub fn macro_def(query: &MacroDef, def: &MacroDef) -> String {
+    let what = if def.is_def { "defines" } else { "invokes" };
+    match query.name {
+        Some(name) => format!("{what} `{name}`"),
+        None => format!("any macro, {what} one")
+    }
+}
+
+pub fn type_def(query: &TypeDef) -> String {
+    let mut reasons = Vec::new();
+    if let Some(name) = query.name { reasons.push(format!("named `{name}`")) }
+    if !query.params.is_empty() { reasons.push(format!("with {n} type parameters", n = query.params.len())) }
+    if let Some(ty) = query.ty { reasons.push(format!("standing for `{ty}`")) }
+    join(reasons, "any type alias or extern type")
+}
+
+/// The trait `sig` is a method of an implementation of, and why it matched the method of `query`
+/// it did.
+pub fn impl_method(query: &ImplQuery, sig: &FnSignature) -> String {
+    let block = if query.methods.is_empty() {
+        format!("in an `impl {name}` block", name = query.trait_name)
+    } else {
+        format!("in an `impl {name}` block having every method of the query", name = query.trait_name)
+    };
+    match query.matched_by(sig) {
+        Some(method) => format!("{block}, {reasons}", reasons = method.explain(sig)),
+        None => block
+    }
+}
+
+#[inline]
+pub fn derived_impl(query: &ImplQuery) -> String {
+    format!("derives `{name}`", name = query.trait_name)
+}
@@ -7,6 +7,7 @@ use syn::{
 };
 
 use crate::{skip_tokens, to_static_str};
+use crate::normalize::type_to_static_str;
 
 pub enum FieldsKind {
     Named,
@@ -30,6 +31,14 @@ pub struct Field {
     pub ty: Option::<&'static str>
 }
 
+impl Field {
+    /// Checks whether `other` matches `self` as a query field, where a missing name or type is a hole.
+    #[inline]
+    pub fn matches(&self, other: &Field) -> bool {
+        self.name.is_none_or(|name| other.name == Some(name)) && self.ty.is_none_or(|ty| other.ty == Some(ty))
+    }
+}
+
 pub type FieldsNamed = Vec::<Field>;
 pub type FieldsUnnamed = Vec::<Field>;
 
@@ -62,7 +71,7 @@ impl From::<syn::Fields> for Fields {
         let mut fields = Vec::with_capacity(fs.len());
         fs.into_iter().for_each(|f| {
             let name = f.ident.as_ref().map(to_static_str);
-            let ty = Some(to_static_str(&f.ty));
+            let ty = Some(type_to_static_str(f.ty));
             let f = Field {name, ty};
             fields.push(f);
         });
@@ -74,30 +83,29 @@ impl From::<syn::Fields> for Fields {
     }
 }
 
+/// Parses a query field: `name: Type`, `Type`, or either of those with `_` as a hole, so
+/// `name: _` only looks at the name and `_: Type` only looks at the type.
 pub fn parse_optionaly_named_field(input: ParseStream) -> syn::Result::<Field> {
-    let name = if input.peek2(Token![:]) && !input.peek3(Token![:]) {
-        let name = input.parse::<Ident>().unwrap();
+    let name = if (input.peek(Ident) || input.peek(Token![_])) && input.peek2(Token![:]) && !input.peek3(Token![:]) {
+        let name = if input.peek(Token![_]) {
+            skip_tokens!(input, _);
+            None
+        } else {
+            Some(input.parse::<Ident>().unwrap())
+        };
         skip_tokens!(input, :);
-        Some(name)
+        name
     } else {
         None
     };
 
-    let ty = if input.is_empty() {
+    let ty = if input.is_empty() || input.peek(Token![,]) {
         None
     } else {
         Some(input.parse::<Type>()?)
     };
 
-    let ty = ty.as_ref().map(to_static_str);
-    let f = if let Some(name) = name {
-        Field {
-            name: Some(to_static_str(&name)),
-            ty,
-        }
-    } else {
-        Field {name: None, ty}
-    };
-
-    Ok(f)
+    let ty = ty.filter(|ty| !matches!(ty, Type::Infer(..))).map(type_to_static_str);
+    let name = name.as_ref().map(to_static_str);
+    Ok(Field {name, ty})
 }
@@ -3,13 +3,22 @@ use std::fmt::{Debug, Formatter};
 use quote::{quote, ToTokens};
 use proc_macro2::TokenStream;
 use syn::{
+    Pat,
     Type,
     Token,
-    parse::{Parse, ParseStream}
+    Ident,
+    PatIdent,
+    TraitBound,
+    token::Paren,
+    ext::IdentExt,
+    parse::{Parse, ParseStream, discouraged::Speculative}
 };
 
+use crate::config::config;
 use crate::{skip_tokens, to_boxed_string};
+use crate::normalize::{unify, has_holes, strip_refs, Bindings, type_to_string};
 
+#[allow(clippy::box_collection)]
 pub struct FnArg {
     pub name: Option::<Box::<String>>,
     pub ty: Option::<Box::<String>>
@@ -29,24 +38,241 @@ impl Debug for FnArg {
 impl ToTokens for FnArg {
     #[inline(always)]
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let ref name = self.name;
-        let ref ty = self.ty;
+        let name = &self.name;
+        let ty = &self.ty;
         tokens.extend(quote!(#name: #ty));
     }
 }
 
+impl FnArg {
+    /// The module path of a `tokio::*` query type, which matches any type under `tokio`.
+    #[inline]
+    pub fn path_prefix(&self) -> Option::<&str> {
+        self.ty.as_deref()?.strip_suffix("::*")
+    }
+
+    /// Whether this is a `self`, `&self` or `&mut self` receiver, or a `self: Type` one.
+    #[inline]
+    pub fn is_receiver(&self) -> bool {
+        self.name.as_deref().is_some_and(|name| name == "self") ||
+            (self.name.is_none() && self.ty.as_deref().is_some_and(|ty| strip_refs(ty) == "self"))
+    }
+
+    /// Whether this query argument has its name checked, see `name_matches`.
+    #[inline]
+    pub fn has_name_to_match(&self) -> bool {
+        config().match_arg_names && self.name.as_deref().is_some_and(|name| name != "_")
+    }
+
+    /// Checks whether `other` has the name of `self` as a query, only with `--match-arg-names`:
+    /// a query argument without a name, or named `_`, matches any. Names are lowercased.
+    #[inline]
+    pub fn name_matches(&self, other: &FnArg) -> bool {
+        !self.has_name_to_match() || self.name == other.name
+    }
+
+    /// Whether this is a query type that can't be compared as a string: a path prefix, a closure
+    /// or a type with `_` holes, or a tuple with any of those as an element.
+    #[inline]
+    pub fn is_pattern(&self) -> bool {
+        self.path_prefix().is_some() || self.ty.as_deref().is_some_and(|ty| {
+            has_holes(ty) || closure_parts(ty).is_some() || impl_bounds(ty).is_some() || tuple_patterns(ty).is_some()
+        })
+    }
+
+    /// Checks whether the type of `other` matches the type of `self` as a query. A path prefix is
+    /// checked against the path the type is written with in the candidate, references stripped:
+    /// it's the types that are looked at, not the module the function is declared in.
+    /// A closure matches any closure of the same family, `Fn`, `FnMut` or `FnOnce`, taken by
+    /// `impl`, `dyn` or a generic parameter, and its signature too if the query gives one, holes
+    /// and generic parameters included.
+    /// Any other `impl` type matches an `impl` type with at least its bounds, in any order, so
+    /// `impl Send + Sync` matches `impl Sync + Send + 'static`.
+    /// A `_` hole, like in `Vec<_>`, matches any type in its place. The elements of a tuple with
+    /// any of those in it are matched one by one.
+    #[inline]
+    pub fn matches(&self, other: &FnArg) -> bool {
+        self.matches_in(other, &mut Bindings::default())
+    }
+
+    /// Like `matches`, with the generic parameters of the candidate's signature in `bindings`.
+    pub fn matches_in(&self, other: &FnArg, bindings: &mut Bindings) -> bool {
+        if let Some(elems) = self.ty.as_deref().and_then(|ty| tuple_patterns(ty)) {
+            let Some(other_elems) = other.ty.as_deref().and_then(|ty| tuple_elems(ty)) else { return false };
+            return elems.len() == other_elems.len() && elems.iter().zip(other_elems).all(|(elem, other)| {
+                elem.matches_in(&FnArg{name: None, ty: Some(Box::new(other.to_owned()))}, bindings)
+            })
+        }
+        if let Some((family, sig)) = self.ty.as_deref().and_then(|ty| closure_parts(ty)) {
+            let Some((other_family, other_sig)) = other.ty.as_deref().and_then(|ty| closure_parts(ty)) else { return false };
+            return family == other_family && (sig.is_empty() || unify(&sig, &other_sig, bindings))
+        }
+        if let Some(bounds) = self.ty.as_deref().and_then(|ty| impl_bounds(ty)) {
+            let Some(other_bounds) = other.ty.as_deref().and_then(|ty| impl_bounds(ty)) else { return false };
+            // Each bound unified on a copy of the bindings, kept only if it matched.
+            return bounds.iter().all(|bound| other_bounds.iter().any(|other| {
+                let mut attempt = bindings.clone();
+                unify(bound, other, &mut attempt) && { *bindings = attempt; true }
+            }))
+        }
+        let Some(prefix) = self.path_prefix() else {
+            return match (self.ty.as_deref(), other.ty.as_deref()) {
+                (Some(ty), Some(other)) => unify(ty, other, bindings),
+                (ty, other) => ty == other
+            }
+        };
+        let Some(ty) = other.ty.as_deref() else { return false };
+        let ty = strip_refs(ty);
+        ty.trim_start_matches("::")
+            .strip_prefix(prefix.trim_start_matches("::"))
+            .is_some_and(|rest| rest.starts_with("::"))
+    }
+}
+
+/// Splits a closure type string, like `impl fnmut(u32)->bool`, `&dyn fn()` or `fnonce`, into its
+/// family and its signature, the latter being empty if not given.
+fn closure_parts(ty: &str) -> Option::<(String, String)> {
+    let ty = strip_refs(ty);
+    let closure = ty.strip_prefix("impl ").or_else(|| ty.strip_prefix("dyn ")).unwrap_or(&ty);
+    let end = closure.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(closure.len());
+    let (family, sig) = closure.split_at(end);
+    if !matches!(family, "fn" | "fnmut" | "fnonce") || !(sig.is_empty() || sig.starts_with('(')) { return None }
+    Some((family.to_owned(), sig.to_owned()))
+}
+
+/// Splits an `impl` type string, like `impl iterator<item=u8>+send`, into its bounds.
+fn impl_bounds(ty: &str) -> Option::<Vec::<&str>> {
+    let bounds = ty.strip_prefix("impl ")?;
+    let (mut split, mut depth, mut start) = (Vec::new(), 0usize, 0);
+    let mut chars = bounds.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '-' if chars.peek().is_some_and(|(_, c)| *c == '>') => _ = chars.next(),
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth = depth.saturating_sub(1),
+            '+' if depth == 0 => {
+                split.push(&bounds[start..i]);
+                start = i + 1
+            }
+            _ => {}
+        }
+    }
+    split.push(&bounds[start..]);
+    Some(split)
+}
+
+/// Splits a tuple type string, like `(u32,(u8,u8))` or `(u32,)`, into its elements.
+fn tuple_elems(ty: &str) -> Option::<Vec::<&str>> {
+    let inner = ty.strip_prefix('(')?.strip_suffix(')')?;
+    let (mut elems, mut depth, mut start) = (Vec::new(), 0usize, 0);
+    let mut chars = inner.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '-' if chars.peek().is_some_and(|(_, c)| *c == '>') => _ = chars.next(),
+            '<' | '(' | '[' => depth += 1,
+            // The opening paren was closed before the end, like in `(a)->(b)`, so not a tuple.
+            '>' | ')' | ']' if depth == 0 => return None,
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                elems.push(&inner[start..i]);
+                start = i + 1
+            }
+            _ => {}
+        }
+    }
+    if start < inner.len() { elems.push(&inner[start..]) }
+    Some(elems)
+}
+
+/// The elements of a tuple query type, if any of them can't be compared as a string.
+fn tuple_patterns(ty: &str) -> Option::<Vec::<FnArg>> {
+    let elems = tuple_elems(ty)?.into_iter().map(|elem| FnArg{name: None, ty: Some(Box::new(elem.to_owned()))}).collect::<Vec::<_>>();
+    elems.iter().any(FnArg::is_pattern).then_some(elems)
+}
+
+/// Parses a query type into the string it's compared by. The elements of a tuple are parsed one
+/// by one, so that they can be path prefixes too.
+fn parse_type_string(input: ParseStream) -> syn::Result::<String> {
+    if let Some(prefix) = parse_path_prefix(input) { return Ok(prefix) }
+    if !input.peek(Paren) { return Ok(type_to_string(parse_type(input)?)) }
+
+    let content;
+    syn::parenthesized!(content in input);
+    let mut elems = Vec::new();
+    let mut trailing_comma = false;
+    while !content.is_empty() {
+        elems.push(parse_type_string(&content)?);
+        trailing_comma = content.parse::<Token![,]>().is_ok();
+        if !trailing_comma { break }
+    }
+    if !content.is_empty() { return Err(content.error("expected `,` or `)`")) }
+    // `(T)` is just `T` in parens, `(T,)` is a tuple.
+    Ok(match elems.as_slice() {
+        [elem] if !trailing_comma => elem.clone(),
+        [elem] => format!("({elem},)"),
+        _ => format!("({})", elems.join(","))
+    })
+}
+
+/// Parses a query type, which can be a bare closure trait with a signature: `syn` only parses
+/// `FnMut(u32) -> bool` as a type after `dyn` or `impl`, so it's parsed as a `dyn` one.
+pub fn parse_type(input: ParseStream) -> syn::Result::<Type> {
+    let is_closure = input.peek2(Paren) && input.fork().parse::<Ident>().is_ok_and(|ident| {
+        ident == "Fn" || ident == "FnMut" || ident == "FnOnce"
+    });
+    if !is_closure { return input.parse() }
+    let bound = input.parse::<TraitBound>()?;
+    Ok(Type::TraitObject(syn::parse_quote!(dyn #bound)))
+}
+
+/// Parses a `tokio::*` query type, which isn't valid Rust so `syn` can't parse it as a `Type`.
+fn parse_path_prefix(input: ParseStream) -> Option::<String> {
+    let fork = input.fork();
+    let mut segments = Vec::new();
+    let leading_colons = fork.parse::<Token![::]>().is_ok();
+    loop {
+        segments.push(fork.call(Ident::parse_any).ok()?.to_string().to_lowercase());
+        fork.parse::<Token![::]>().ok()?;
+        if fork.parse::<Token![*]>().is_ok() { break }
+    }
+    if !fork.is_empty() && !fork.peek(Token![,]) { return None }
+    input.advance_to(&fork);
+    Some(format!("{colons}{path}::*", colons = if leading_colons { "::" } else { "" }, path = segments.join("::")))
+}
+
+/// Parses the pattern of an argument pasted from code along with its `:`, like `mut x:` or
+/// `(a, b):`, if there's one. Only a plain binding, `mut` or `ref` stripped, names the argument,
+/// as it does for the arguments of the indexed functions.
+#[allow(clippy::box_collection)]
+fn parse_pattern(input: ParseStream) -> Option::<Box::<String>> {
+    let fork = input.fork();
+    let pat = Pat::parse_single(&fork).ok()?;
+    if !fork.peek(Token![:]) || fork.peek(Token![::]) { return None }
+    fork.parse::<Token![:]>().ok()?;
+    input.advance_to(&fork);
+    match pat {
+        Pat::Ident(PatIdent { ident, subpat: None, .. }) => Some(to_boxed_string(&ident)),
+        _ => None
+    }
+}
+
 impl Parse for FnArg {
     fn parse(input: ParseStream) -> syn::Result::<Self> {
-        let name = input.parse::<Type>()?;
-        let (name, ty) = if input.peek(Token![:]) {
+        let is_named = (input.peek(Ident::peek_any) || input.peek(Token![_])) &&
+            input.peek2(Token![:]) &&
+            !input.peek2(Token![::]);
+        let name = if is_named {
+            let name = if input.peek(Token![_]) {
+                to_boxed_string(&input.parse::<Token![_]>()?)
+            } else {
+                to_boxed_string(&input.call(Ident::parse_any)?)
+            };
             skip_tokens!(input, :);
-            (Some(name.into_token_stream()), Some(input.parse()?))
+            Some(name)
         } else {
-            (None, Some(name))
+            parse_pattern(input)
         };
-
-        let name = name.map(|stream| to_boxed_string(&stream));
-        let ty = Some(to_boxed_string(&ty));
+        let ty = Some(Box::new(parse_type_string(input)?));
         Ok(FnArg{name, ty})
     }
 }
@@ -3,17 +3,41 @@ use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 
 use quote::ToTokens;
+use proc_macro2::Delimiter;
 use syn::{
     Ident,
     Token,
+    Type,
+    ItemFn,
+    Path,
+    Generics,
     ImplItemFn,
     Signature,
+    WherePredicate,
+    TypeParamBound,
+    WhereClause,
+    token::Brace,
     parse::{Parse, ParseStream}
 };
 
 use crate::loc::Loc;
+use crate::attrs::Attrs;
+use crate::config::config;
+use crate::namepat::{NamePattern, unescape_stars};
+use crate::normalize::{
+    is_reference,
+    strip_refs,
+    query_vars,
+    first_type_arg,
+    self_ty_matches,
+    Bindings,
+    bound_traits,
+    type_param_bounds,
+    canonicalize,
+    type_to_string
+};
 use crate::ReturnType;
-use crate::fnarg::FnArg;
+use crate::fnarg::{FnArg, parse_type};
 use crate::{
     skip_tokens,
     inputs_to_string,
@@ -24,11 +48,64 @@ use crate::{
 pub struct FnSignature {
     name: Option::<String>,
     inputs: Vec::<FnArg>,
-    output: ReturnType
+    output: ReturnType,
+    has_receiver: bool,
+    /// Whether the declared return type is a reference, remembered as `--ignore-refs` strips it.
+    returns_ref: bool,
+    /// Set for a query without an argument list, like `fn` or `fn foo`, which matches any signature.
+    is_bare: bool,
+    /// Set for a query with the `&` modifier or `--loose-inputs`, see `strip_refs`.
+    loose_inputs: bool,
+    /// Set for a query with the `&` modifier or `--loose-output`, see `strip_refs`.
+    loose_output: bool,
+    name_pattern: Option::<NamePattern>,
+    /// The type parameters of the function and of its `impl` block, which match any type. Those
+    /// bounded by a closure trait are not in there, they are matched as closures. For a query, its
+    /// type variables, see `query_vars`.
+    generics: Vec::<String>,
+    /// Whether the function or its `impl` block has type parameters, closure-bounded ones included.
+    is_generic: bool,
+    /// The traits the type parameters are bounded by, see `bound_traits`.
+    bounds: Vec::<&'static str>,
+    /// The traits each type parameter is bounded by, see `type_param_bounds`. For a query, those
+    /// of the type variables it declares, like `T` in `fn<T: Iterator>(T) -> u32`.
+    param_bounds: Vec::<(String, Vec::<&'static str>)>,
+    /// The self type of the `impl` block a method is declared in.
+    self_ty: Option::<&'static str>,
+    /// The header of the `impl` block a method is declared in, like `impl<t: clone> repo<t>`,
+    /// see `impl_header`.
+    impl_header: Option::<&'static str>,
+    /// The trait the `impl` block a method is declared in implements, by the last segment of its
+    /// path, like `iterator`.
+    impl_trait: Option::<&'static str>,
+    attrs: Attrs
 }
 
 pub type FnSigs<'a> = Vec::<(Loc<'a>, FnSignature)>;
-pub type FnSigMap<'a> = HashMap::<FnSignature, Loc<'a>>;
+/// Signatures of one file, by the signature they compare equal to. Different functions of a file
+/// can have the same signature, so every one of them is kept along with its own name.
+pub type FnSigMap<'a, 'b> = HashMap::<&'a FnSignature, Vec::<(&'a Loc<'b>, &'a FnSignature)>>;
+
+/// How far `--suggest` loosens a query that matched nothing, every level includes the ones before it.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Loosening {
+    Output,
+    Refs,
+    Order
+}
+
+impl Loosening {
+    pub const CASCADE: [Loosening; 3] = [Loosening::Output, Loosening::Refs, Loosening::Order];
+
+    #[inline]
+    pub fn ignored(&self) -> &'static str {
+        match self {
+            Self::Output => "the return type",
+            Self::Refs => "the return type and references",
+            Self::Order => "the return type, references and argument order"
+        }
+    }
+}
 
 impl Debug for FnSignature {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -45,33 +122,421 @@ impl Debug for FnSignature {
     }
 }
 
-impl From::<Signature> for FnSignature {
+/// Which parts of a signature matched a query, see `FnSignature::matched_parts`.
+pub struct MatchedParts {
+    pub name: bool,
+    pub inputs: Vec::<bool>,
+    pub output: bool
+}
+
+impl FnSignature {
     #[inline(always)]
-    fn from(syn_sig: Signature) -> Self {
+    pub fn name(&self) -> Option::<&str> {
+        self.name.as_deref()
+    }
+
+    #[inline(always)]
+    pub fn returns_ref(&self) -> bool {
+        self.returns_ref
+    }
+
+    /// Whether this is an associated function of an `impl` block rather than a method, taking no
+    /// `self` receiver, like `new`.
+    #[inline]
+    pub fn is_associated_fn(&self) -> bool {
+        self.self_ty.is_some() && !self.has_receiver
+    }
+
+    /// Whether this is a query without an argument list, like `fn` or `fn foo`.
+    #[inline(always)]
+    pub fn is_bare(&self) -> bool {
+        self.is_bare
+    }
+
+    #[inline(always)]
+    pub fn generics(&self) -> &[String] {
+        &self.generics
+    }
+
+    #[inline(always)]
+    pub fn is_generic(&self) -> bool {
+        self.is_generic
+    }
+
+    #[inline(always)]
+    pub fn bounds(&self) -> &[&'static str] {
+        &self.bounds
+    }
+
+    #[inline(always)]
+    pub fn attrs(&self) -> Attrs {
+        self.attrs
+    }
+
+    #[inline(always)]
+    pub fn impl_header(&self) -> Option::<&'static str> {
+        self.impl_header
+    }
+
+    #[inline(always)]
+    pub fn impl_trait(&self) -> Option::<&'static str> {
+        self.impl_trait
+    }
+
+    /// Checks whether `other` has the name of `self` as a query, compared whole, unlike `matches`
+    /// which only checks name patterns, like `get*`. A query without a name matches any.
+    #[inline]
+    pub fn has_name_of(&self, other: &FnSignature) -> bool {
+        self.name_pattern.is_some() || self.name.as_deref().is_none_or(|name| other.name().is_some_and(|other| other.eq_ignore_ascii_case(name)))
+    }
+
+    /// Whether this is a constructor of the type named `ty`, as `--constructors` finds them: an
+    /// associated function of that type, taking no `self`, that returns `Self` or the type itself,
+    /// possibly wrapped in an `Option` or a `Result`, like `new`, `with_capacity` or `try_from`.
+    pub fn is_constructor_of(&self, ty: &str) -> bool {
+        if self.has_receiver || !self.self_ty.is_some_and(|self_ty| self_ty_matches(ty, self_ty)) { return false }
+        let is_self = |output: &str| output == "self" || self_ty_matches(ty, output);
+        let output = self.output.to_canonical_string();
+        is_self(&output) || (
+            (output.starts_with("option<") || output.starts_with("result<")) &&
+                first_type_arg(&output).is_some_and(is_self)
+        )
+    }
+
+    /// Whether this returns a `Result`, by the last segment of the path of the outermost return
+    /// type, so `io::Result<T>` and `fmt::Result` count while `Option<Result<T, E>>` doesn't.
+    pub fn is_fallible(&self) -> bool {
+        let output = self.output.to_canonical_string();
+        let path = output.split('<').next().unwrap_or_default();
+        path.rsplit("::").next() == Some("result")
+    }
+
+    #[inline(always)]
+    pub fn inputs(&self) -> &[FnArg] {
+        &self.inputs
+    }
+
+    #[inline(always)]
+    pub fn output(&self) -> &ReturnType {
+        &self.output
+    }
+
+    #[inline]
+    pub fn output_is_wildcard(&self) -> bool {
+        self.output.to_canonical_string() == "_"
+    }
+
+    /// Whether the query can be looked up in a `FnSigMap`, otherwise `matches` has to be used.
+    /// Generic candidates have to be matched with `matches` either way, and every candidate with
+    /// `--suffix-paths`, that makes paths match more than what they compare equal to.
+    #[inline]
+    pub fn is_exact(&self) -> bool {
+        !self.is_bare &&
+            !config().suffix_paths &&
+            self.generics.is_empty() &&
+            self.name_pattern.is_none() &&
+            !self.output_arg().is_pattern() &&
+            !self.has_receiver &&
+            !self.loose_inputs &&
+            !self.loose_output &&
+            !self.inputs.iter().any(|arg| arg.is_pattern() || arg.has_name_to_match())
+    }
+
+    /// Makes this query match references and the types they point to interchangeably, like
+    /// `--ignore-refs` does, for this query only, in its arguments, its return type or both: the
+    /// references of the candidates are then stripped when they are compared, as they were
+    /// indexed with theirs.
+    pub fn strip_refs(&mut self, inputs: bool, output: bool) {
+        self.loose_inputs |= inputs;
+        self.loose_output |= output;
+        if !inputs { return }
+        self.inputs.iter_mut().for_each(|arg| {
+            if let Some(ref mut ty) = arg.ty { **ty = strip_refs(ty) }
+        })
+    }
+
+    /// Checks whether `other` matches `self` as a query, honoring name patterns, `tokio::*` path
+    /// prefixes and `_` holes, `-> _` included. The type variables of the query and the generic
+    /// parameters of `other` match any type, the same one everywhere in the signature, so
+    /// `fn(Vec<T>) -> T` matches `fn pop<U>(v: Vec<U>) -> U` and `fn(v: Vec<u8>) -> u8`, but not
+    /// `fn(v: Vec<u8>) -> u32`. Type variables declared with bounds, like in `fn<T: Iterator>(T)`,
+    /// only match generic parameters with those bounds, see `param_bounds_match`. A query with a
+    /// `self` receiver only matches methods.
+    pub fn matches(&self, other: &FnSignature) -> bool {
+        if let Some(ref pattern) = self.name_pattern {
+            if !other.name().is_some_and(|name| pattern.matches(name)) { return false }
+        }
+        self.is_bare || self.match_bindings(other).is_some()
+    }
+
+    /// Matches the arguments and the return type of `other` against the ones of `self` as a
+    /// query, returning what the variables on both sides are bound to if they match.
+    fn match_bindings<'a>(&'a self, other: &'a FnSignature) -> Option::<Bindings<'a>> {
+        if self.has_receiver && !other.has_receiver { return None }
+        if self.inputs.len() != other.inputs.len() { return None }
+        let stripped;
+        let other_inputs = if self.loose_inputs {
+            stripped = other.inputs.iter().map(|arg| FnArg {
+                name: arg.name.clone(),
+                ty: arg.ty.as_deref().map(|ty| Box::new(strip_refs(ty)))
+            }).collect::<Vec::<_>>();
+            &stripped
+        } else {
+            &other.inputs
+        };
+        let mut bindings = self.bindings(other);
+        if !self.inputs.iter().zip(other_inputs).all(|(arg, other)| arg.name_matches(other) && arg.matches_in(other, &mut bindings)) {
+            return None
+        }
+
+        (self.output_matches(other, &mut bindings) && self.param_bounds_match(other, &bindings)).then_some(bindings)
+    }
+
+    /// Tells why `other` matches `self` as a query, for `--explain`: whether it's the exact same
+    /// signature, or else what the variables are bound to and how the query was loosened.
+    pub fn explain(&self, other: &FnSignature) -> String {
+        let mut reasons = Vec::new();
+        if let Some(ref pattern) = self.name_pattern { reasons.push(format!("name matches `{pattern}`")) }
+        if self.is_bare {
+            if reasons.is_empty() { reasons.push("any signature".to_owned()) }
+            return reasons.join(", ")
+        }
+        if self == other && !self.loose_inputs && !self.loose_output {
+            reasons.push("exact signature".to_owned());
+            return reasons.join(", ")
+        }
+        if let Some(bindings) = self.match_bindings(other) { reasons.extend(bindings.describe()) }
+        if self.loose_inputs { reasons.push("references ignored in the arguments".to_owned()) }
+        if self.loose_output { reasons.push("references ignored in the return type".to_owned()) }
+        if self.output_is_wildcard() {
+            reasons.push("any return type".to_owned())
+        } else if self.inputs.iter().chain([&self.output_arg()]).any(FnArg::is_pattern) {
+            reasons.push("holes, closures or path prefixes matched by structure".to_owned())
+        }
+        if !self.param_bounds.is_empty() { reasons.push("bounds of the type variables met".to_owned()) }
+        if reasons.is_empty() { reasons.push("same types".to_owned()) }
+        reasons.join(", ")
+    }
+
+    /// Checks that every type variable the query declares with bounds is bound to a generic
+    /// parameter of `other` with at least those bounds, in any order, compared by trait name only,
+    /// or to nothing at all. A concrete type is not looked up for the traits it implements, so it
+    /// doesn't match. `'static` is a bound like a trait, while any other lifetime, like `'a`,
+    /// stands for any lifetime bound, their names being local to each function.
+    fn param_bounds_match(&self, other: &FnSignature, bindings: &Bindings) -> bool {
+        let is_named_lifetime = |bound: &str| bound.starts_with('\'') && bound != "'static";
+        self.param_bounds.iter().all(|(var, bounds)| match bindings.query_var_param(var) {
+            None => true,
+            Some(None) => false,
+            Some(Some(param)) => other.param_bounds.iter()
+                .find(|(p, _)| p == param)
+                .is_some_and(|(_, traits)| bounds.iter().all(|bound| {
+                    traits.contains(bound) || (is_named_lifetime(bound) && traits.iter().any(|tr| tr.starts_with('\'')))
+                }))
+        })
+    }
+
+    #[inline]
+    fn bindings<'a>(&'a self, other: &'a FnSignature) -> Bindings<'a> {
+        Bindings::new(&other.generics).with_query_vars(&self.generics)
+    }
+
+    /// The return type as an argument type, so that it's matched like one: a returned closure,
+    /// like `-> impl Fn(i32) -> i32`, matches by family, then by signature if the query gives one.
+    #[inline]
+    fn output_arg(&self) -> FnArg {
+        let output = self.output.to_canonical_string();
+        let output = if self.loose_output { strip_refs(&output) } else { output };
+        FnArg {name: None, ty: Some(Box::new(output))}
+    }
+
+    /// A `-> _` query matches any return type, none included, other holes need a return type.
+    #[inline]
+    fn output_matches(&self, other: &FnSignature, bindings: &mut Bindings) -> bool {
+        if self.output_is_wildcard() { return true }
+        let other_output = other.output.to_canonical_string();
+        let other_output = if self.loose_output { strip_refs(&other_output) } else { other_output };
+        self.output_arg().matches_in(&FnArg {name: None, ty: Some(Box::new(other_output))}, bindings)
+    }
+
+    /// Reports which parts of `other` matched `self` as a query. The name only counts with a name
+    /// pattern, and an argument counts when any argument of the query matches it, so the parts a
+    /// `--suggest` query matched with its arguments reordered are reported too.
+    pub fn matched_parts(&self, other: &FnSignature) -> MatchedParts {
+        let name = self.name_pattern.as_ref().is_some_and(|pattern| other.name().is_some_and(|name| pattern.matches(name)));
+        let inputs = other.inputs.iter().map(|arg| {
+            !self.is_bare && self.inputs.iter().any(|query| query.matches_in(arg, &mut self.bindings(other)))
+        }).collect();
+        let output = !self.is_bare &&
+            !self.output_is_wildcard() &&
+            !matches!(other.output, ReturnType::Default) &&
+            self.output_matches(other, &mut self.bindings(other));
+        MatchedParts {name, inputs, output}
+    }
+
+    /// Like `matches`, but ignoring what `loosening` says to.
+    pub fn matches_loosely(&self, other: &FnSignature, loosening: Loosening) -> bool {
+        if let Some(ref pattern) = self.name_pattern {
+            if !other.name().is_some_and(|name| pattern.matches(name)) { return false }
+        }
+
+        if self.is_bare { return true }
+        if self.inputs.len() != other.inputs.len() { return false }
+
+        let input_types = |sig: &FnSignature| {
+            let mut types = sig.inputs.iter().map(|arg| {
+                let ty = arg.ty.as_deref().map(String::as_str).unwrap_or_default();
+                if loosening >= Loosening::Refs { strip_refs(ty) } else { ty.to_owned() }
+            }).collect::<Vec::<_>>();
+            if loosening >= Loosening::Order { types.sort_unstable() }
+            types
+        };
+        input_types(self) == input_types(other)
+    }
+
+    /// Number of arguments, counting the receiver only when `--count-self` is set.
+    #[inline]
+    pub fn arg_count(&self) -> usize {
+        self.inputs.len() + (self.has_receiver && config().count_self) as usize
+    }
+
+    /// Checks whether this passes the filters on functions of the config, like `--args`,
+    /// `--generic-only` or `--fallible`, which apply whatever the query is.
+    pub fn passes_filters(&self) -> bool {
+        let config = config();
+        config.arg_count_matches(self.arg_count()) &&
+            (!config.returns_ref || self.returns_ref()) &&
+            (!config.associated_fns_only || self.is_associated_fn()) &&
+            config.genericity_matches(self.is_generic()) &&
+            config.fallibility_matches(self.is_fallible())
+    }
+}
+
+/// Renders the header of an `impl` block, its generics, the trait it implements if any and its
+/// self type, like `impl<t: clone> repo<t>` or `impl<t> display for repo<t>`, the trait being
+/// given with whether it's negative.
+pub fn impl_header(generics: &Generics, tr: Option::<(bool, &Path)>, self_ty: &str) -> &'static str {
+    let render = |tokens: &dyn ToTokens| canonicalize(&tokens.to_token_stream().to_string().to_lowercase());
+    let params = generics.params.iter().map(|param| {
+        render(param).replacen(':', ": ", 1).replace('+', " + ")
+    }).collect::<Vec::<_>>();
+    let generics = if params.is_empty() { String::new() } else { format!("<{}>", params.join(", ")) };
+    let tr = match tr {
+        Some((negative, path)) => format!("{bang}{path} for ", bang = if negative { "!" } else { "" }, path = render(path)),
+        None => String::new()
+    };
+    Box::leak(format!("impl{generics} {tr}{self_ty}").into_boxed_str())
+}
+
+/// Maps the generic parameters of `generics` bounded by a closure trait, in their list or in the
+/// `where` clause, to that bound: `F: FnMut(u32) -> bool` gives `f` to `fnmut(u32)->bool`.
+fn closure_bounds(generics: &Generics) -> HashMap::<String, String> {
+    let params = generics.type_params().map(|param| (&param.ident, &param.bounds));
+    let predicates = generics.where_clause.iter().flat_map(|clause| clause.predicates.iter()).filter_map(|pred| {
+        let WherePredicate::Type(pred) = pred else { return None };
+        let Type::Path(ref ty) = pred.bounded_ty else { return None };
+        Some((ty.path.get_ident()?, &pred.bounds))
+    });
+    params.chain(predicates).filter_map(|(ident, bounds)| {
+        let closure = bounds.iter().find_map(|bound| match bound {
+            TypeParamBound::Trait(bound) if bound.path.segments.last().is_some_and(|seg| {
+                seg.ident == "Fn" || seg.ident == "FnMut" || seg.ident == "FnOnce"
+            }) => Some(bound),
+            _ => None
+        })?;
+        // Rendered the way `type_to_string` renders `impl` types, so the closure's signature is normalized.
+        let ty = Type::ImplTrait(syn::parse_quote!(impl #closure));
+        Some((ident.to_string().to_lowercase(), type_to_string(ty)))
+    }).collect()
+}
+
+impl FnSignature {
+    /// Converts the signature of a method, whose `impl` block's generics are in scope as well.
+    #[inline]
+    pub fn from_impl_fn(item: ImplItemFn, impl_generics: &Generics, self_ty: &'static str, impl_header: &'static str, impl_trait: Option::<&'static str>) -> Self {
+        FnSignature {
+            attrs: Attrs::from(item.attrs.as_slice()),
+            self_ty: Some(self_ty),
+            impl_header: Some(impl_header),
+            impl_trait,
+            ..FnSignature::from_sig(item.sig, Some(impl_generics))
+        }
+    }
+
+    /// Sets the self type of the `impl` block the function is declared in, see `self_ty`.
+    #[inline]
+    pub fn with_self_ty(self, self_ty: Option::<&'static str>) -> Self {
+        FnSignature {self_ty, ..self}
+    }
+
+    fn from_sig(syn_sig: Signature, outer_generics: Option::<&Generics>) -> Self {
+        let mut closures = outer_generics.map(closure_bounds).unwrap_or_default();
+        closures.extend(closure_bounds(&syn_sig.generics));
+        let mut type_params = outer_generics.into_iter()
+            .flat_map(Generics::type_params)
+            .chain(syn_sig.generics.type_params())
+            .peekable();
+        let is_generic = type_params.peek().is_some();
+        let generics = type_params
+            .map(|param| param.ident.to_string().to_lowercase())
+            .filter(|param| !closures.contains_key(param))
+            .collect();
+        let mut bounds = outer_generics.map(bound_traits).unwrap_or_default();
+        bounds.extend(bound_traits(&syn_sig.generics));
+        let mut param_bounds = outer_generics.map(type_param_bounds).unwrap_or_default();
+        param_bounds.extend(type_param_bounds(&syn_sig.generics));
+        let has_receiver = syn_sig.receiver().is_some();
+        let mut inputs = signature_get_inputs(syn_sig.inputs);
+        // A closure taken by a generic parameter, even by reference, is matched like one taken by `impl`.
+        if !closures.is_empty() {
+            inputs.iter_mut().for_each(|arg| {
+                if let Some(closure) = arg.ty.as_deref().and_then(|ty| closures.get(&strip_refs(ty))) {
+                    arg.ty = Some(Box::new(closure.clone()))
+                }
+            })
+        }
         FnSignature {
             name: Some(syn_sig.ident.to_string().to_lowercase()),
-            inputs: signature_get_inputs(syn_sig.inputs),
-            output: signature_get_output(syn_sig.output)
+            has_receiver,
+            returns_ref: matches!(syn_sig.output, syn::ReturnType::Type(_, ref ty) if is_reference(ty)),
+            inputs,
+            output: signature_get_output(syn_sig.output),
+            is_bare: false,
+            loose_inputs: false,
+            loose_output: false,
+            name_pattern: None,
+            generics,
+            is_generic,
+            bounds,
+            param_bounds,
+            self_ty: None,
+            impl_header: None,
+            impl_trait: None,
+            attrs: Attrs::default()
         }
     }
 }
 
-impl From::<ImplItemFn> for FnSignature {
+impl From::<Signature> for FnSignature {
     #[inline(always)]
-    fn from(item: ImplItemFn) -> Self {
-        FnSignature {
-            name: Some(item.sig.ident.to_string().to_lowercase()),
-            inputs: signature_get_inputs(item.sig.inputs),
-            output: signature_get_output(item.sig.output)
-        }
+    fn from(syn_sig: Signature) -> Self {
+        FnSignature::from_sig(syn_sig, None)
     }
 }
 
+impl From::<ItemFn> for FnSignature {
+    #[inline(always)]
+    fn from(item: ItemFn) -> Self {
+        FnSignature {attrs: Attrs::from(item.attrs.as_slice()), ..FnSignature::from(item.sig)}
+    }
+}
+
+
 impl Hash for FnSignature {
     #[inline(always)]
     fn hash<H: Hasher>(&self, state: &mut H) {
         inputs_to_string(&self.inputs).to_lowercase().hash(state);
-        self.output.to_token_stream().to_string().to_lowercase().hash(state);
+        self.output.to_canonical_string().hash(state);
     }
 }
 
@@ -84,8 +549,8 @@ impl PartialEq for FnSignature {
 
         if self_input_types != other_input_types { return false }
 
-        let self_output = self.output.to_token_stream().to_string().to_lowercase();
-        let other_output = other.output.to_token_stream().to_string().to_lowercase();
+        let self_output = self.output.to_canonical_string();
+        let other_output = other.output.to_canonical_string();
 
         self_output == other_output
     }
@@ -97,29 +562,310 @@ impl Parse for FnSignature {
     fn parse(input: ParseStream) -> syn::Result::<Self> {
         skip_tokens!(input, fn);
 
+        let leading_star = input.parse::<Token![*]>().is_ok();
         let name: Option::<Ident> = if input.peek(Ident) {
             Some(input.parse().unwrap())
         } else {
             None
         };
+        let trailing_star = input.parse::<Token![*]>().is_ok();
+        // Type variables declared with their bounds, like in `fn<T: Iterator>(T) -> u32`.
+        let mut declared = input.parse::<Generics>()?;
+        let name = name.map(|i| unescape_stars(i.to_string()));
+        let name_pattern = NamePattern::new(name.as_deref().unwrap_or_default(), leading_star, trailing_star);
 
-        let content;
-        syn::parenthesized!(content in input);
+        let is_bare = input.is_empty();
         let mut inputs = Vec::new();
-        while !content.is_empty() {
-            let fn_arg = content.parse::<FnArg>()?;
-            inputs.push(fn_arg);
-            if content.peek(Token![,]) {
-                content.parse::<Token![,]>()?;
-            } else { break }
+        let mut has_receiver = false;
+        let output = if is_bare {
+            ReturnType::Default
+        } else {
+            let content;
+            syn::parenthesized!(content in input);
+            while !content.is_empty() {
+                let fn_arg = content.parse::<FnArg>()?;
+                // Candidates keep their receiver apart from their arguments, so the query does too.
+                if inputs.is_empty() && !has_receiver && fn_arg.is_receiver() {
+                    has_receiver = true
+                } else {
+                    inputs.push(fn_arg)
+                }
+                if content.peek(Token![,]) {
+                    content.parse::<Token![,]>()?;
+                } else { break }
+            }
+            // Parsed like an argument type, so that a bare closure, like `-> Fn(i32) -> i32`, works.
+            match input.parse::<Token![->]>() {
+                Ok(arrow) => signature_get_output(syn::ReturnType::Type(arrow, Box::new(parse_type(input)?))),
+                Err(_) => ReturnType::Default
+            }
+        };
+        // What follows the signature of a function pasted from code: its `where` clause, which
+        // bounds type variables like the declared ones, then its body or the `;` of a declaration.
+        if !is_bare {
+            declared.where_clause = input.parse::<Option::<WhereClause>>()?;
+            if input.peek(Brace) {
+                input.step(|cursor| cursor.group(Delimiter::Brace).map(|(_, _, rest)| ((), rest)).ok_or_else(|| cursor.error("expected a body")))?
+            } else {
+                skip_tokens!(input, ;);
+            }
         }
+        let output_string = output.to_canonical_string();
+        let types = inputs.iter().filter_map(|arg| arg.ty.as_deref().map(String::as_str));
+        let mut generics = declared.type_params().map(|param| param.ident.to_string().to_lowercase()).collect::<Vec::<_>>();
+        query_vars(types.chain([output_string.as_str()])).into_iter().for_each(|var| {
+            if !generics.contains(&var) { generics.push(var) }
+        });
 
         let sig = FnSignature {
-            name: name.map(|i| i.to_string()),
+            name,
             inputs,
-            output: signature_get_output(input.parse::<syn::ReturnType>().unwrap()),
+            output,
+            has_receiver,
+            returns_ref: false,
+            is_bare,
+            loose_inputs: false,
+            loose_output: false,
+            name_pattern,
+            generics,
+            is_generic: false,
+            bounds: Vec::new(),
+            param_bounds: type_param_bounds(&declared),
+            self_ty: None,
+            impl_header: None,
+            impl_trait: None,
+            attrs: Attrs::default()
         };
 
         Ok(sig)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config;
+    use crate::index::Index;
+    use crate::item::{Item, parse_query};
+
+    fn parse_fn(query: &str) -> FnSignature {
+        match parse_query(query).unwrap().item {
+            Item::FnSignature(sig) => sig,
+            item => panic!("expected a function query, got {item:?}")
+        }
+    }
+
+    /// The names of the functions of `code` the function query `query` matches.
+    fn matching(query: &str, code: &str) -> Vec::<String> {
+        let query = parse_fn(query);
+        let index = Index::from_sources([("a.rs".into(), code.to_owned())]);
+        index.files[0].0.iter()
+            .filter(|(_, sig)| sig.passes_filters() && query.matches(sig))
+            .filter_map(|(_, sig)| sig.name().map(str::to_owned))
+            .collect()
+    }
+
+    #[test]
+    fn never_returns() {
+        let code = "fn abort() -> ! { loop {} }\nfn id<T>(t: T) -> T { t }\nfn make() -> u8 { 1 }\nfn nothing() {}";
+        assert_eq!(matching("fn() -> !", code), ["abort"]);
+        assert_eq!(matching("fn() -> T", code), ["make"]);
+        assert_eq!(matching("fn(T) -> T", code), ["id"]);
+        assert_eq!(matching("fn()", code), ["nothing"]);
+        let index = Index::from_sources([("a.rs".into(), code.to_owned())]);
+        assert_eq!(parse_fn("fn() -> !"), index.files[0].0[0].1);
+    }
+
+    #[test]
+    fn unit() {
+        let code = "fn take(x: ()) {}\nfn unit() -> () {}\nfn nothing() {}\nfn make() -> u8 { 1 }";
+        assert_eq!(matching("fn(())", code), ["take"]);
+        assert_eq!(matching("fn() -> ()", code), ["unit", "nothing"]);
+        assert_eq!(matching("fn()", code), ["unit", "nothing"]);
+        assert_eq!(parse_fn("fn() -> ()"), parse_fn("fn()"));
+    }
+
+    #[test]
+    fn transformations() {
+        let code = "
+            fn pop<U>(v: Vec<U>) -> U { todo!() }
+            fn first<T>(v: Vec<T>) -> Option<T> { todo!() }
+            fn last(v: Vec<u8>) -> u8 { todo!() }
+            fn len(v: Vec<u8>) -> u32 { todo!() }
+            fn unwrap<T>(o: Option<T>) -> T { todo!() }
+            fn max<T: Ord>(a: T, b: T) -> T { todo!() }
+            fn add(a: u8, b: u8) -> u8 { a + b }
+            fn shift(a: u8, b: u32) -> u8 { a << b }
+        ";
+        assert_eq!(matching("fn(Vec<T>) -> T", code), ["pop", "last"]);
+        assert_eq!(matching("fn(Vec<T>) -> Option<T>", code), ["first"]);
+        assert_eq!(matching("fn(Option<T>) -> T", code), ["unwrap"]);
+        assert_eq!(matching("fn(T, T) -> T", code), ["max", "add"]);
+    }
+
+    #[test]
+    fn strict_mut() {
+        let code = "fn read(v: &Vec<u8>) {}\nfn write(v: &mut Vec<u8>) {}\nfn own(v: Vec<u8>) {}";
+        assert_eq!(matching("fn(&mut Vec<u8>)", code), ["write"]);
+        assert_eq!(matching("fn(&Vec<u8>)", code), ["read"]);
+        config::with_config(&["--ignore-refs"], || {
+            assert_eq!(matching("fn(&mut Vec<u8>)", code), ["read", "write", "own"]);
+        });
+        config::with_config(&["--ignore-refs", "--strict-mut"], || {
+            assert_eq!(matching("fn(&mut Vec<u8>)", code), ["write"]);
+            assert_eq!(matching("fn(&Vec<u8>)", code), ["read", "own"]);
+            assert_eq!(matching("fn(Vec<u8>)", code), ["read", "own"]);
+        });
+    }
+
+    #[test]
+    fn reference_lifetimes() {
+        let code = "fn g<'a>(x: &'a str) -> &'a str { x }\nfn h(x: &'static mut str) {}";
+        assert_eq!(matching("fn(&str) -> &str", code), ["g"]);
+        assert_eq!(matching("fn(&'b str) -> &'b str", code), ["g"]);
+        assert_eq!(matching("fn(&mut str)", code), ["h"]);
+        config::with_config(&["--full-parse"], || assert_eq!(matching("fn(&str) -> &str", code), ["g"]));
+        config::with_config(&["--ignore-refs", "--strict-mut"], || {
+            assert_eq!(matching("fn(&mut str)", code), ["h"]);
+            assert_eq!(matching("fn(str) -> str", code), ["g"]);
+        });
+    }
+
+    #[test]
+    fn generic_only() {
+        let code = "fn map<T, U>(t: T, f: fn(T) -> U) -> U { f(t) }\nfn double(x: u32) -> u32 { x * 2 }";
+        assert_eq!(matching("fn", code), ["map", "double"]);
+        config::with_config(&["--generic-only"], || assert_eq!(matching("fn", code), ["map"]));
+        config::with_config(&["--non-generic-only"], || assert_eq!(matching("fn", code), ["double"]));
+    }
+
+    #[test]
+    fn arrays_as_slices() {
+        let code = "fn slice(buf: &[u8]) {}\nfn array_ref(buf: &[u8; 32]) {}\nfn array(buf: [u8; 32]) {}";
+        assert_eq!(matching("fn(&[u8])", code), ["slice"]);
+        assert_eq!(matching("fn(&[u8; 32])", code), ["array_ref"]);
+        config::with_config(&["--arrays-as-slices"], || {
+            assert_eq!(matching("fn(&[u8])", code), ["slice", "array_ref"]);
+            assert_eq!(matching("fn(&[u8; 32])", code), ["slice", "array_ref"]);
+            assert_eq!(matching("fn([u8; 32])", code), ["array"]);
+        });
+    }
+
+    #[test]
+    fn bounds_only() {
+        let code = "
+            fn sum<I: Iterator<Item = u32>>(i: I) -> u32 { i.sum() }
+            fn count<I>(i: I) -> u32 where I: Iterator + Clone { 0 }
+            fn len<T: AsRef<[u8]>>(t: T) -> u32 { 0 }
+            fn any<T>(t: T) -> u32 { 0 }
+        ";
+        assert_eq!(matching("fn<T: Iterator>(T) -> u32", code), ["sum", "count"]);
+        assert_eq!(matching("fn<T: Iterator + Clone>(T) -> u32", code), ["count"]);
+        assert_eq!(matching("fn<T: Iterator>(T) -> _", code), ["sum", "count"]);
+    }
+
+    #[test]
+    fn fallible() {
+        let code = "
+            fn open(path: &Path) -> io::Result<File> { todo!() }
+            fn parse(s: &str) -> Result<u8, ParseIntError> { todo!() }
+            fn find(s: &str) -> Option<usize> { None }
+            fn nested(s: &str) -> Option<Result<u8, Error>> { None }
+            fn len(s: &str) -> usize { 0 }
+            fn print(s: &str) {}
+        ";
+        config::with_config(&["--fallible"], || assert_eq!(matching("fn", code), ["open", "parse"]));
+        config::with_config(&["--infallible"], || assert_eq!(matching("fn", code), ["find", "nested", "len", "print"]));
+    }
+
+    #[test]
+    fn pasted_signatures() {
+        let code = "
+            pub async fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<TcpStream> { todo!() }
+            pub fn from_utf8(vec: Vec<u8>) -> Result<String, FromUtf8Error> { todo!() }
+            pub const unsafe fn from_raw_parts<'a, T>(data: *const T, len: usize) -> &'a [T] { todo!() }
+            fn spawn<F>(f: F) -> JoinHandle<F::Output> where F: Future + Send + 'static { todo!() }
+        ";
+        let pasted = [
+            ("pub async fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<TcpStream> {", "connect"),
+            ("pub fn from_utf8(vec: Vec<u8>) -> Result<String, FromUtf8Error> { Ok(String::new()) }", "from_utf8"),
+            ("pub const unsafe fn from_raw_parts<'a, T>(data: *const T, len: usize) -> &'a [T]", "from_raw_parts"),
+            ("pub fn spawn<F>(f: F) -> JoinHandle<F::Output>\nwhere\n    F: Future + Send + 'static,\n{", "spawn")
+        ];
+        for (query, name) in pasted {
+            assert_eq!(matching(query, code), [name], "{query}");
+        }
+    }
+
+    #[test]
+    fn associated_fns_only() {
+        let code = "
+            impl Buf {
+                fn new() -> Self { todo!() }
+                fn len(&self) -> usize { 0 }
+            }
+            fn free() -> usize { 0 }
+        ";
+        assert_eq!(matching("fn", code), ["new", "len", "free"]);
+        config::with_config(&["--associated-fns-only"], || assert_eq!(matching("fn", code), ["new"]));
+    }
+
+    #[test]
+    fn impl_trait_bounds() {
+        let code = "
+            fn both() -> impl Sync + Send { todo!() }
+            fn send() -> impl Send { todo!() }
+            fn more() -> impl Send + Sync + Unpin { todo!() }
+            fn boxed() -> Box<dyn Send> { todo!() }
+        ";
+        assert_eq!(matching("fn() -> impl Send", code), ["both", "send", "more"]);
+        assert_eq!(matching("fn() -> impl Send + Sync", code), ["both", "more"]);
+        assert_eq!(matching("fn() -> impl Sync + Send", code), ["both", "more"]);
+        assert_eq!(matching("fn() -> impl Unpin + Sync + Send", code), ["more"]);
+    }
+
+    #[test]
+    fn pattern_args() {
+        let code = "fn dist((a, b): (u32, u32)) -> u32 { a + b }\nfn ignore(_: u32) {}\nfn named(x: u32) {}";
+        let index = Index::from_sources([("a.rs".into(), code.to_owned())]);
+        let args = index.files[0].0.iter()
+            .flat_map(|(_, sig)| sig.inputs())
+            .map(|arg| (arg.name.as_deref().map(String::as_str), arg.ty.as_deref().map(String::as_str)))
+            .collect::<Vec::<_>>();
+        assert_eq!(args, [(None, Some("(u32,u32)")), (None, Some("u32")), (Some("x"), Some("u32"))]);
+        assert_eq!(matching("fn((u32, u32)) -> u32", code), ["dist"]);
+        assert_eq!(matching("fn(_: u32)", code), ["ignore", "named"]);
+        assert_eq!(matching("fn(u32)", code), ["ignore", "named"]);
+    }
+
+    #[test]
+    fn lifetime_bounds() {
+        let code = "
+            fn leak<T: 'static>(t: T) {}
+            fn spawn<T: Send + 'static>(t: T) {}
+            fn store<T>(t: T) where T: 'static + Send + Sync {}
+            fn send<T: Send>(t: T) {}
+        ";
+        assert_eq!(matching("fn<T: 'static>(T)", code), ["leak", "spawn", "store"]);
+        assert_eq!(matching("fn<T: Send + 'static>(T)", code), ["spawn", "store"]);
+        assert_eq!(matching("fn<T: 'static + Send>(T)", code), ["spawn", "store"]);
+        assert_eq!(matching("fn<T: Sync + 'static + Send>(T)", code), ["store"]);
+    }
+
+    #[test]
+    fn suffix_paths() {
+        let code = "
+            fn std_err() -> std::io::Error { todo!() }
+            fn tokio_err() -> tokio::io::Error { todo!() }
+            fn fmt_err() -> std::fmt::Error { todo!() }
+            fn bare_err() -> Error { todo!() }
+        ";
+        assert_eq!(matching("fn() -> io::Error", code), Vec::<String>::new());
+        config::with_config(&["--suffix-paths"], || {
+            assert_eq!(matching("fn() -> io::Error", code), ["std_err", "tokio_err"]);
+            assert_eq!(matching("fn() -> Error", code), ["std_err", "tokio_err", "fmt_err", "bare_err"]);
+            assert_eq!(matching("fn() -> o::Error", code), Vec::<String>::new());
+        });
+    }
+}
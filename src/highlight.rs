@@ -0,0 +1,174 @@
+//! Renders matched items with the parts that matched the query emphasized, for `--highlight`,
+//! and renders items and queries as plain text, for their `Display` impls in `results`.
+//!
+//! Emphasis is bold, and only used when stdout is a terminal and `NO_COLOR` is not set, otherwise
+//! the same rendering comes out as plain text.
+
+use std::env;
+use std::sync::OnceLock;
+use std::io::{self, IsTerminal};
+
+use crate::item::Item;
+use crate::ReturnType;
+use crate::normalize::display_type;
+use crate::fields::{Field, Fields};
+use crate::enumdef::EnumDef;
+use crate::constdef::ConstDef;
+use crate::structdef::StructDef;
+use crate::traitdef::TraitDef;
+use crate::macrodef::MacroDef;
+use crate::typedef::TypeDef;
+use crate::fnsig::{FnSignature, MatchedParts};
+
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+fn colors_enabled() -> bool {
+    static ENABLED: OnceLock::<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none())
+}
+
+#[inline]
+fn emphasize(s: &str, matched: bool) -> String {
+    if matched && colors_enabled() {
+        format!("{BOLD}{s}{RESET}")
+    } else {
+        s.to_owned()
+    }
+}
+
+/// Renders `sig` as `fn name(types) -> type`, emphasizing what `query` matched in it.
+#[inline]
+pub fn fn_signature(query: &FnSignature, sig: &FnSignature) -> String {
+    render_fn(sig, Some(&query.matched_parts(sig)))
+}
+
+/// Renders `def` as `struct name { field: type }` or `struct name(type)`, emphasizing the fields
+/// `query` asked for.
+#[inline]
+pub fn struct_def(query: &StructDef, def: &StructDef) -> String {
+    render_struct(def, |f| query.fields.iter().any(|qf| qf.matches(f)))
+}
+
+/// Renders a parsed query as plain text, normalized the way it's compared, holes as `_`.
+pub fn query(query: &Item) -> String {
+    match query {
+        Item::FnSignature(sig) if sig.is_bare() => match sig.name() {
+            Some(name) => format!("fn {name}"),
+            None => "fn".to_owned()
+        }
+        Item::FnSignature(sig) => sig.to_string(),
+        Item::StructDef(def) => def.to_string(),
+        Item::EnumDef(def) => def.to_string(),
+        Item::ConstDef(def) => def.to_string(),
+        Item::TraitDef(def) => def.to_string(),
+        Item::MacroDef(def) => match def.name {
+            Some(name) => format!("macro {name}"),
+            None => "macro".to_owned()
+        }
+        Item::TypeDef(def) => def.to_string(),
+        Item::ImplQuery(query) => query.to_string()
+    }
+}
+
+pub fn render_fn(sig: &FnSignature, parts: Option::<&MatchedParts>) -> String {
+    let inputs = sig.inputs().iter().enumerate().map(|(i, arg)| {
+        let matched = parts.is_some_and(|parts| parts.inputs.get(i).copied().unwrap_or_default());
+        emphasize(&arg.ty.as_deref().map_or("_".to_owned(), |ty| display_type(ty)), matched)
+    }).collect::<Vec::<_>>().join(", ");
+    let output = display_type(&sig.output().to_canonical_string());
+    let output = if matches!(sig.output(), ReturnType::Default) {
+        String::new()
+    } else {
+        format!(" -> {}", emphasize(&output, parts.is_some_and(|parts| parts.output)))
+    };
+    let fun = match sig.name() {
+        Some(name) => format!("fn {name}({inputs}){output}", name = emphasize(name, parts.is_some_and(|parts| parts.name))),
+        None => format!("fn({inputs}){output}")
+    };
+    match sig.impl_header() {
+        Some(header) => format!("{header} :: {fun}"),
+        None => fun
+    }
+}
+
+pub fn render_field(f: &Field) -> String {
+    let ty = f.ty.map_or("_".to_owned(), display_type);
+    match f.name {
+        Some(name) => format!("{name}: {ty}"),
+        None => ty.to_owned()
+    }
+}
+
+pub fn render_struct(def: &StructDef, matched: impl Fn(&Field) -> bool) -> String {
+    let fields = def.fields.iter().map(|f| emphasize(&render_field(f), matched(f))).collect::<Vec::<_>>().join(", ");
+    let name = def.name.unwrap_or("_");
+    if def.is_tup == Some(true) {
+        format!("struct {name}({fields})")
+    } else if fields.is_empty() {
+        format!("struct {name}")
+    } else {
+        format!("struct {name} {{ {fields} }}")
+    }
+}
+
+pub fn render_enum(def: &EnumDef) -> String {
+    let variants = def.variants.iter().map(|v| {
+        let name = v.name.unwrap_or("_");
+        let fields = v.fields.iter().map(render_field).collect::<Vec::<_>>().join(", ");
+        match v.fields {
+            Fields::Named(..) => format!("{name} {{ {fields} }}"),
+            Fields::Unnamed(..) => format!("{name}({fields})"),
+            Fields::Unit => name.to_owned()
+        }
+    }).collect::<Vec::<_>>().join(", ");
+    let name = def.name.unwrap_or("_");
+    if variants.is_empty() {
+        format!("enum {name}")
+    } else {
+        format!("enum {name} {{ {variants} }}")
+    }
+}
+
+pub fn render_const(def: &ConstDef) -> String {
+    let name = def.name.unwrap_or("_");
+    let ty = def.ty.map_or("_".to_owned(), display_type);
+    match def.self_ty {
+        Some(self_ty) => format!("const {self_ty}::{name}: {ty}"),
+        None => format!("const {name}: {ty}")
+    }
+}
+
+pub fn render_trait(def: &TraitDef) -> String {
+    let assoc_types = def.assoc_types.iter().map(|ty| {
+        let name = ty.name.unwrap_or("_");
+        match ty.default {
+            Some(default) => format!("type {name} = {default};", default = display_type(default)),
+            None => format!("type {name};")
+        }
+    }).collect::<Vec::<_>>().join(" ");
+    let name = def.name.unwrap_or("_");
+    if assoc_types.is_empty() {
+        format!("trait {name}")
+    } else {
+        format!("trait {name} {{ {assoc_types} }}")
+    }
+}
+
+pub fn render_macro(def: &MacroDef) -> String {
+    let name = def.name.unwrap_or("_");
+    if def.is_def {
+        format!("macro_rules! {name}")
+    } else {
+        format!("{name}!")
+    }
+}
+
+pub fn render_type(def: &TypeDef) -> String {
+    let name = def.name.unwrap_or("_");
+    let params = if def.params.is_empty() { String::new() } else { format!("<{}>", def.params.join(", ")) };
+    match def.ty {
+        Some(ty) => format!("type {name}{params} = {ty}", ty = display_type(ty)),
+        None => format!("type {name}{params}")
+    }
+}
@@ -0,0 +1,72 @@
+use indexmap::IndexMap;
+use syn::{
+    Path,
+    Token,
+    parse::{Parse, ParseStream}
+};
+
+use crate::loc::Loc;
+use crate::attrs::DERIVABLE;
+use crate::structmap::Xx64Hasher;
+use crate::fnsig::FnSignature;
+use crate::{skip_tokens, to_static_str};
+
+/// A query for the `impl` blocks of a trait having some methods, like
+/// `impl Iterator { fn next(&mut self) -> Option<_> }`, which finds the methods of the blocks
+/// implementing `Iterator` where every method of the query matches one. The trait is compared by
+/// the last segment of its path, and the methods of the query by their whole name when they
+/// have one, unlike function queries: the point is to have that method.
+/// A query without methods of one of the `DERIVABLE` std traits, like `impl Clone`, finds the
+/// structs and enums deriving it too, derived impls having no methods to index.
+#[derive(Debug)]
+pub struct ImplQuery {
+    pub trait_name: &'static str,
+    pub methods: Vec::<FnSignature>
+}
+
+impl ImplQuery {
+    /// The method of the query `method` matches, if any.
+    #[inline]
+    pub fn matched_by(&self, method: &FnSignature) -> Option::<&FnSignature> {
+        self.methods.iter().find(|query| query.has_name_of(method) && query.matches(method))
+    }
+
+    /// Whether a struct or an enum deriving `derives` implements the trait as the query asks.
+    #[inline]
+    pub fn derived_by(&self, derives: &[&str]) -> bool {
+        self.methods.is_empty() && DERIVABLE.contains(&self.trait_name) && derives.contains(&self.trait_name)
+    }
+
+    /// Finds the methods of the `impl` blocks of the trait where every method of the query
+    /// matches one, those that matched, or every method of the blocks for a query without any.
+    pub fn search<'a, 'b>(&self, fnsigs: impl Iterator<Item = &'b (Loc<'a>, FnSignature)>) -> Vec::<&'b (Loc<'a>, FnSignature)> {
+        // Methods are grouped by block, a block being told apart by its file and its header.
+        let mut blocks = IndexMap::<_, Vec::<_>, Xx64Hasher>::default();
+        fnsigs.filter(|(_, sig)| sig.impl_trait() == Some(self.trait_name)).for_each(|item| {
+            blocks.entry((item.0.path(), item.1.impl_header())).or_default().push(item)
+        });
+        blocks.into_values().filter(|methods| {
+            self.methods.iter().all(|query| methods.iter().any(|(_, sig)| query.has_name_of(sig) && query.matches(sig)))
+        }).flat_map(|methods| {
+            methods.into_iter().filter(|(_, sig)| self.methods.is_empty() || self.matched_by(sig).is_some())
+        }).collect()
+    }
+}
+
+impl Parse for ImplQuery {
+    fn parse(input: ParseStream) -> syn::Result::<Self> {
+        skip_tokens!(input, impl);
+        let path = input.parse::<Path>()?;
+        let trait_name = path.segments.last().map(|seg| to_static_str(&seg.ident)).unwrap_or_default();
+        let mut methods = Vec::new();
+        if !input.is_empty() {
+            let content;
+            syn::braced!(content in input);
+            while !content.is_empty() {
+                methods.push(content.parse::<FnSignature>()?);
+                skip_tokens!(content, ,);
+            }
+        }
+        Ok(ImplQuery {trait_name, methods})
+    }
+}
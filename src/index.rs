@@ -0,0 +1,138 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use syn::{File, Item};
+
+use crate::parse;
+use crate::skim::skim;
+use crate::config::config;
+use crate::equiv::set_source_aliases;
+use crate::fnsig::FnSigs;
+use crate::enumdef::EnumDefs;
+use crate::constdef::ConstDefs;
+use crate::traitdef::TraitDefs;
+use crate::macrodef::MacroDefs;
+use crate::typedef::TypeDefs;
+use crate::structdef::StructDefs;
+use crate::recover::recover_items;
+
+/// Everything indexed from one source file, or from one rustdoc JSON file.
+pub type FileItems<'a> = (FnSigs<'a>, StructDefs<'a>, EnumDefs<'a>, ConstDefs<'a>, TraitDefs<'a>, MacroDefs<'a>, TypeDefs<'a>);
+
+pub struct Index<'a> {
+    pub files: Vec::<FileItems<'a>>,
+    /// The `.rs` files the directory walk found, kept so that the index can be built again from
+    /// them without walking again.
+    pub paths: Vec::<PathBuf>
+}
+
+impl Index<'static> {
+    /// Indexes `(path, code)` pairs, wherever they come from: the directory walk is just one
+    /// producer of them, a test can as well build a corpus inline. The paths are leaked, as the
+    /// locations of the indexed items point to them for as long as the program runs.
+    /// With `--expand-aliases`, every source is parsed before any is indexed, so that the types of
+    /// each file are normalized with the type aliases of all of them, see `set_source_aliases`.
+    pub fn from_sources(sources: impl IntoIterator<Item = (PathBuf, String)>) -> Self {
+        let asts = sources.into_iter().filter_map(|(file_path, code)| {
+            let file_path = &*Box::leak(Box::new(file_path));
+            Some((file_path, parse_source(file_path, &code)?))
+        });
+        let files = if config().expand_aliases {
+            let asts = asts.collect::<Vec::<_>>();
+            set_source_aliases(asts.iter().flat_map(|(_, ast)| &ast.items).filter_map(|item| match item {
+                Item::Type(t) => Some(t),
+                _ => None
+            }));
+            asts.into_iter().map(|(file_path, ast)| parse(file_path, ast)).collect()
+        } else {
+            asts.map(|(file_path, ast)| parse(file_path, ast)).collect()
+        };
+        Self {files, paths: Vec::new()}
+    }
+}
+
+impl Index<'_> {
+    /// Number of indexed items of all kinds.
+    pub fn items_count(&self) -> usize {
+        self.files.iter().map(|(fnsigs, defs, edefs, cdefs, tdefs, mdefs, tydefs)| {
+            fnsigs.len() + defs.len() + edefs.len() + cdefs.len() + tdefs.len() + mdefs.len() + tydefs.len()
+        }).sum()
+    }
+}
+
+/// Parses `code` with the bodies of its functions stripped, see `skim`, unless `--full-parse` is
+/// set, then as it is if that fails, and then falls back to parsing its top-level items one by
+/// one. Returns `None` if not a single item could be parsed.
+#[inline]
+pub fn parse_source(file_path: &Path, code: &str) -> Option::<File> {
+    let skimmed = if config().full_parse { None } else { skim(code) };
+    skimmed.and_then(|skimmed| syn::parse_str::<File>(&skimmed).ok())
+        .or_else(|| syn::parse_str::<File>(code).ok())
+        .or_else(|| recover_items(file_path, code))
+}
+
+/// Reads the source file at `path`. A file that isn't valid UTF-8 is skipped, or read with the
+/// invalid bytes replaced by U+FFFD when `--lossy` is set. Both are reported when `--verbose` is.
+pub fn read_source(path: &Path) -> Option::<String> {
+    let config = config();
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            if config.verbose { eprintln!("[{path}: could not read: {e}]", path = path.display()) }
+            return None
+        }
+    };
+    match String::from_utf8(bytes) {
+        Ok(code) => Some(code),
+        Err(e) if config.lossy => {
+            if config.verbose { eprintln!("[{path}: not valid UTF-8, read lossily]", path = path.display()) }
+            Some(String::from_utf8_lossy(e.as_bytes()).into_owned())
+        }
+        Err(_) => {
+            if config.verbose { eprintln!("[{path}: not valid UTF-8, skipped]", path = path.display()) }
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_sources() {
+        let index = Index::from_sources([
+            ("a.rs".into(), "fn connect(addr: SocketAddr) -> io::Result<TcpStream> { todo!() }\nstruct Config { timeout: Duration }".to_owned()),
+            ("b.rs".into(), "enum Color { Red, Green }\nimpl Config {\n    const MAX: usize = 100;\n    fn new() -> Self { Self { timeout: Duration::ZERO } }\n}".to_owned())
+        ]);
+        assert_eq!(index.files.len(), 2);
+        assert_eq!(index.items_count(), 5);
+        let (fnsigs, defs, ..) = &index.files[0];
+        assert_eq!(fnsigs[0].1.name(), Some("connect"));
+        assert_eq!((fnsigs[0].0.path().to_str(), fnsigs[0].0.line()), (Some("a.rs"), 1));
+        assert_eq!((defs[0].1.name, defs[0].0.line()), (Some("config"), 2));
+        let (fnsigs, _, edefs, cdefs, ..) = &index.files[1];
+        assert_eq!(edefs[0].1.name, Some("color"));
+        assert_eq!((fnsigs[0].1.name(), fnsigs[0].0.line()), (Some("new"), 4));
+        assert_eq!(cdefs[0].0.line(), 3);
+    }
+
+    #[test]
+    fn unparsable_sources_are_skipped() {
+        let index = Index::from_sources([("a.rs".into(), "fn (".to_owned()), ("b.rs".into(), "fn f() {}".to_owned())]);
+        assert_eq!(index.files.len(), 1);
+        assert_eq!(index.files[0].0[0].0.path().to_str(), Some("b.rs"));
+    }
+
+    #[test]
+    fn non_utf8_sources() {
+        let path = std::env::temp_dir().join(format!("roogle-non-utf8-{pid}.rs", pid = std::process::id()));
+        fs::write(&path, b"// caf\xe9\nfn brew(beans: u8) -> u8 { beans }\n").unwrap();
+        assert_eq!(read_source(&path), None);
+        let code = crate::config::with_config(&["--lossy"], || read_source(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(code.starts_with("// caf\u{fffd}\n"));
+        let index = Index::from_sources([(path, code)]);
+        assert_eq!(index.files[0].0[0].1.name(), Some("brew"));
+    }
+}
@@ -1,26 +1,176 @@
-use syn::{Token, parse::{Parse, ParseStream}};
+use syn::{Abi, Token, Attribute, Visibility, parse::{Parse, ParseStream}};
 
+use crate::config::config;
+use crate::namepat::escape_stars;
 use crate::enumdef::EnumDef;
+use crate::constdef::ConstDef;
 use crate::fnsig::FnSignature;
 use crate::structdef::StructDef;
+use crate::traitdef::TraitDef;
+use crate::macrodef::MacroDef;
+use crate::typedef::TypeDef;
+use crate::implquery::ImplQuery;
 
 #[derive(Debug)]
 pub enum Item {
     EnumDef(EnumDef),
     StructDef(StructDef),
-    FnSignature(FnSignature)
+    FnSignature(FnSignature),
+    ConstDef(ConstDef),
+    TraitDef(TraitDef),
+    MacroDef(MacroDef),
+    TypeDef(TypeDef),
+    ImplQuery(ImplQuery)
+}
+
+/// Modifiers written before the kind of a query, each one turning a flag on for that query only,
+/// so one `--repl` session can mix strict and loose searches:
+///
+/// - `~` retries a function query that matched nothing more loosely, like `--suggest`,
+/// - `!` prints the items that do not match instead, like `--invert`,
+/// - `&` matches references and the types they point to interchangeably, like `--ignore-refs`,
+///   for function queries only.
+///
+/// They can come in any order, like in `~&fn(Config) -> bool`, each one at most once.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Modifiers {
+    pub suggest: bool,
+    pub invert: bool,
+    pub ignore_refs: bool
+}
+
+impl Parse for Modifiers {
+    fn parse(input: ParseStream) -> syn::Result::<Self> {
+        let mut modifiers = Self::default();
+        loop {
+            let modifier = if input.peek(Token![~]) {
+                input.parse::<Token![~]>()?;
+                &mut modifiers.suggest
+            } else if input.peek(Token![!]) {
+                input.parse::<Token![!]>()?;
+                &mut modifiers.invert
+            } else if input.peek(Token![&&]) {
+                return Err(input.error("query modifier given twice"))
+            } else if input.peek(Token![&]) {
+                input.parse::<Token![&]>()?;
+                &mut modifiers.ignore_refs
+            } else if input.peek(Token![#]) {
+                // The attributes of an item pasted from code.
+                break
+            } else if let Some((punct, _)) = input.cursor().punct() {
+                return Err(input.error(format!("unknown query modifier `{punct}`, expected `~`, `!` or `&`")))
+            } else {
+                break
+            };
+            if *modifier { return Err(input.error("query modifier given twice")) }
+            *modifier = true
+        }
+        Ok(modifiers)
+    }
+}
+
+/// A query with its modifiers.
+#[derive(Debug)]
+pub struct Query {
+    pub modifiers: Modifiers,
+    pub item: Item
+}
+
+/// Skips what can come before an item kind in code pasted as a query: attributes, visibility and
+/// the qualifiers of a function, like `#[inline] pub(crate) const unsafe extern "C" fn`. `const` is
+/// only a qualifier before `fn`, `async`, `unsafe` or `extern`, otherwise it's a constant query.
+fn skip_qualifiers(input: ParseStream) -> syn::Result::<()> {
+    input.call(Attribute::parse_inner)?;
+    input.call(Attribute::parse_outer)?;
+    input.parse::<Visibility>()?;
+    loop {
+        if input.peek(Token![const]) && (input.peek2(Token![fn]) || input.peek2(Token![async]) || input.peek2(Token![unsafe]) || input.peek2(Token![extern])) {
+            input.parse::<Token![const]>()?;
+        } else if input.peek(Token![async]) {
+            input.parse::<Token![async]>()?;
+        } else if input.peek(Token![unsafe]) {
+            input.parse::<Token![unsafe]>()?;
+        } else if input.peek(Token![extern]) {
+            input.parse::<Abi>()?;
+        } else {
+            return Ok(())
+        }
+    }
+}
+
+/// Parses `query`, which can be a whole function pasted from code, its doc comments, attributes
+/// and body included, or only the start of it, like `pub fn get(&self, key: &str) -> Option<&V> {`:
+/// a brace left open can't even be tokenized, so the query is parsed again cut before the body
+/// when it fails, see `signature_end`.
+pub fn parse_query(query: &str) -> syn::Result::<Query> {
+    let query = &*escape_stars(query);
+    syn::parse_str::<Query>(query).or_else(|e| {
+        let Some(end) = signature_end(query) else { return Err(e) };
+        match syn::parse_str::<Query>(&query[..end]) {
+            Ok(query @ Query {item: Item::FnSignature(..), ..}) => Ok(query),
+            _ => Err(e)
+        }
+    })
+}
+
+/// Where the body of a function pasted from code starts: at the first `{` out of the parens and
+/// brackets, so not at the one of an argument pattern like `Point { x, y }: Point`, and out of the
+/// `//` comments, doc comments included.
+fn signature_end(query: &str) -> Option::<usize> {
+    let mut depth = 0usize;
+    let mut chars = query.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '/' if chars.peek().is_some_and(|(_, c)| *c == '/') => while chars.next_if(|(_, c)| *c != '\n').is_some() {},
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            '{' if depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+impl Parse for Query {
+    fn parse(input: ParseStream) -> syn::Result::<Self> {
+        let modifiers = input.parse::<Modifiers>()?;
+        skip_qualifiers(input)?;
+        if modifiers.ignore_refs && !input.peek(Token![fn]) {
+            return Err(input.error("the `&` query modifier only applies to function queries"))
+        }
+        let mut item = input.parse::<Item>()?;
+        if let Item::FnSignature(ref mut fnsig) = item {
+            let (inputs, output) = (config().loose_inputs, config().loose_output);
+            fnsig.strip_refs(modifiers.ignore_refs || inputs, modifiers.ignore_refs || output)
+        }
+        Ok(Query {modifiers, item})
+    }
 }
 
 impl Parse for Item {
     fn parse(input: ParseStream) -> syn::Result::<Self> {
+        skip_qualifiers(input)?;
         if input.parse::<Token![fn]>().is_ok() {
             Ok(Item::FnSignature(input.parse()?))
         } else if input.parse::<Token![struct]>().is_ok() {
             Ok(Item::StructDef(input.parse()?))
         } else if input.parse::<Token![enum]>().is_ok() {
             Ok(Item::EnumDef(input.parse()?))
+        } else if input.parse::<Token![const]>().is_ok() {
+            Ok(Item::ConstDef(input.parse()?))
+        } else if input.parse::<Token![trait]>().is_ok() {
+            Ok(Item::TraitDef(input.parse()?))
+        } else if input.parse::<Token![macro]>().is_ok() {
+            Ok(Item::MacroDef(input.parse()?))
+        } else if input.parse::<Token![type]>().is_ok() {
+            Ok(Item::TypeDef(input.parse()?))
+        } else if input.parse::<Token![impl]>().is_ok() {
+            if config().no_impl_methods {
+                return Err(input.error("`impl` queries need the methods of `impl` blocks, which `--no-impl-methods` skips"))
+            }
+            Ok(Item::ImplQuery(input.parse()?))
         } else {
-            panic!("unexpected input: {input}, expected `fn` or `struct` at the beginning")
+            Err(input.error("expected `fn`, `struct`, `enum`, `const`, `trait`, `macro`, `type` or `impl` at the beginning"))
         }
     }
 }
@@ -0,0 +1,238 @@
+use std::path::PathBuf;
+
+use quote::ToTokens;
+use proc_macro2::TokenStream;
+use syn::{
+    Pat,
+    File,
+    Type,
+    Token,
+    PatType,
+    PatIdent,
+    ItemImpl,
+    ImplItem,
+    spanned::Spanned,
+    punctuated::Punctuated
+};
+
+pub mod loc;
+use loc::*;
+pub mod item;
+pub mod fnsig;
+use fnsig::*;
+pub mod fnarg;
+use fnarg::*;
+pub mod fields;
+pub mod dir_rec;
+pub mod enummap;
+pub mod enumdef;
+use enumdef::*;
+pub mod structmap;
+pub mod structdef;
+use structdef::*;
+pub mod constdef;
+use constdef::*;
+pub mod traitdef;
+use traitdef::*;
+pub mod macrodef;
+use macrodef::*;
+pub mod typedef;
+use typedef::*;
+pub mod config;
+use config::*;
+pub mod normalize;
+use normalize::*;
+pub mod cache;
+pub mod rustdoc;
+pub mod results;
+pub mod namepat;
+pub mod recover;
+pub mod skim;
+pub mod highlight;
+pub mod explain;
+pub mod equiv;
+pub mod implquery;
+pub mod index;
+use index::*;
+pub mod attrs;
+pub mod workspace;
+pub mod markdown;
+pub mod template;
+
+#[macro_export]
+macro_rules! skip_tokens {
+    ($content: expr, $($t: tt), *) => {
+        $(_ = $content.parse::<Token![$t]>();)*
+    };
+}
+
+#[inline(always)]
+pub fn to_boxed_string<T: ToTokens>(x: &T) -> Box::<String> {
+    Box::new(x.to_token_stream().to_string().to_lowercase())
+}
+
+#[inline(always)]
+pub fn to_static_str<T: ToTokens>(x: &T) -> &'static str {
+    Box::leak(x.to_token_stream().to_string().to_lowercase().into_boxed_str())
+}
+
+pub enum ReturnType {
+    Default,
+    Type(Box::<Type>)
+}
+
+impl ReturnType {
+    /// The string return types are hashed and compared by, the counterpart of `type_to_string`.
+    #[inline]
+    pub fn to_canonical_string(&self) -> String {
+        canonicalize(&self.to_token_stream().to_string().to_lowercase())
+    }
+}
+
+impl ToTokens for ReturnType {
+    #[inline]
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        *tokens = self.to_token_stream()
+    }
+
+    fn to_token_stream(&self) -> TokenStream {
+        match self {
+            Self::Default => TokenStream::new(),
+            Self::Type(ty) => ty.to_token_stream()
+        }
+    }
+
+    #[inline]
+    fn into_token_stream(self) -> TokenStream
+    where
+        Self: Sized
+    {
+        self.to_token_stream()
+    }
+}
+
+pub fn signature_get_inputs(inputs: Punctuated::<syn::FnArg, Token![,]>) -> Vec::<FnArg> {
+    inputs.into_iter().filter_map(|fn_arg| {
+        match fn_arg {
+            syn::FnArg::Receiver(..) => None,
+            syn::FnArg::Typed(PatType { pat, ty, .. }) => {
+                let ty = Some(Box::new(type_to_string(*ty)));
+                if let Pat::Ident(PatIdent { ident, .. }) = *pat {
+                    let name = Some(to_boxed_string(&ident));
+                    Some(FnArg{name, ty})
+                } else {
+                    let name = None;
+                    Some(FnArg{name, ty})
+                }
+            }
+        }
+    }).collect()
+}
+
+#[inline]
+pub fn signature_get_output(output: syn::ReturnType) -> ReturnType {
+    match output {
+        syn::ReturnType::Default => ReturnType::Default,
+        syn::ReturnType::Type(.., ty) => match normalize(*ty) {
+            ty if is_unit(&ty) => ReturnType::Default,
+            ty => ReturnType::Type(Box::new(ty))
+        }
+    }
+}
+
+#[inline]
+pub fn inputs_to_string(inputs: &[FnArg]) -> String {
+    inputs.iter().map(|FnArg{ty, ..}| quote::quote!(#ty).to_string()).collect()
+}
+
+/// Methods are skipped when `--no-impl-methods` is set, associated constants are always collected.
+fn impl_get_items<'a>(file_path: &'a PathBuf, im: ItemImpl) -> (FnSigs<'a>, ConstDefs<'a>) {
+    let self_ty = type_to_static_str(*im.self_ty);
+    let header = impl_header(&im.generics, im.trait_.as_ref().map(|(bang, path, _)| (bang.is_some(), path)), self_ty);
+    let impl_trait = im.trait_.as_ref()
+        .filter(|(bang, ..)| bang.is_none())
+        .and_then(|(_, path, _)| path.segments.last())
+        .map(|seg| to_static_str(&seg.ident));
+    let generics = im.generics;
+    let (mut fnsigs, mut cdefs) = (FnSigs::new(), ConstDefs::new());
+    im.items.into_iter().for_each(|item| {
+        let span = item.span();
+        match item {
+            ImplItem::Fn(f) if !config().no_impl_methods => {
+                fnsigs.push((Loc::from_span(file_path, &span), FnSignature::from_impl_fn(f, &generics, self_ty, header, impl_trait)))
+            },
+            ImplItem::Const(c) => {
+                cdefs.push((Loc::from_span(file_path, &span), ConstDef::from_impl_item(c, self_ty)))
+            },
+            _ => {}
+        }
+    });
+    (fnsigs, cdefs)
+}
+
+fn parse(file_path: &PathBuf, ast: File) -> FileItems<'_> {
+    let size = ast.items.len() / 2;
+    let map = ast.items.into_iter().fold((
+        FnSigs::with_capacity(size),
+        StructDefs::with_capacity(size),
+        EnumDefs::with_capacity(size),
+        ConstDefs::new(),
+        TraitDefs::new(),
+        MacroDefs::new(),
+        TypeDefs::new()
+    ), |(mut fnsigs, mut defs, mut edefs, mut cdefs, mut tdefs, mut mdefs, mut tydefs), syn_item| {
+        let span = syn_item.span();
+        match syn_item {
+            syn::Item::Fn(f) => {
+                let loc = Loc::from_span(file_path, &span);
+                let sig = FnSignature::from(f);
+                fnsigs.push((loc, sig));
+            }
+            syn::Item::Struct(s) => {
+                let loc = Loc::from_span(file_path, &span);
+                let def = StructDef::from(s);
+                defs.push((loc, def));
+            }
+            syn::Item::Enum(e) => {
+                let loc = Loc::from_span(file_path, &span);
+                let def = EnumDef::from(e);
+                edefs.push((loc, def));
+            }
+            syn::Item::Const(c) => {
+                let loc = Loc::from_span(file_path, &span);
+                let def = ConstDef::from(c);
+                cdefs.push((loc, def));
+            }
+            syn::Item::Trait(t) => {
+                let loc = Loc::from_span(file_path, &span);
+                let def = TraitDef::from(t);
+                tdefs.push((loc, def));
+            }
+            syn::Item::Macro(m) => {
+                let loc = Loc::from_span(file_path, &span);
+                let def = MacroDef::from(m);
+                mdefs.push((loc, def));
+            }
+            syn::Item::Type(t) => {
+                let loc = Loc::from_span(file_path, &span);
+                let def = TypeDef::from(t);
+                tydefs.push((loc, def));
+            }
+            syn::Item::ForeignMod(fm) => fm.items.into_iter().for_each(|item| {
+                if let syn::ForeignItem::Type(t) = item {
+                    let loc = Loc::from_span(file_path, &t.span());
+                    tydefs.push((loc, TypeDef::from(t)))
+                }
+            }),
+            syn::Item::Impl(im) => {
+                let (impl_fnsigs, impl_cdefs) = impl_get_items(file_path, im);
+                fnsigs.extend(impl_fnsigs);
+                cdefs.extend(impl_cdefs);
+            }
+            _ => {}
+        } (fnsigs, defs, edefs, cdefs, tdefs, mdefs, tydefs)
+    });
+    map
+}
+
+pub type Results<'a, 'b> = Vec::<&'a Loc<'b>>;
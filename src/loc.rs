@@ -1,24 +1,101 @@
-use std::path::PathBuf;
+use std::fs;
+use std::ops::Range;
+use std::borrow::Cow;
+use std::sync::OnceLock;
+use std::path::{self, Path, PathBuf};
 use std::fmt::{Debug, Display, Formatter};
 
 use proc_macro2::Span;
 
-#[derive(Eq, Hash, Clone, PartialEq)]
-pub struct Loc<'a>(&'a PathBuf, usize, usize);
-//                 file_path,   line,  column
+use crate::config::config;
+
+#[derive(Eq, Ord, Hash, Clone, PartialEq, PartialOrd)]
+pub struct Loc<'a>(&'a PathBuf, usize, usize, usize,    usize);
+//                 file_path,   line,  column, end_line, end_column
 
 impl<'a > Loc<'a> {
+    #[inline(always)]
+    pub fn new(file_path: &'a PathBuf, start: (usize, usize), end: (usize, usize)) -> Self {
+        Loc(file_path, start.0, start.1, end.0, end.1)
+    }
+
     #[inline(always)]
     pub fn from_span(file_path: &'a PathBuf, span: &Span) -> Self {
-        let linecol = span.start();
-        Loc(file_path, linecol.line, linecol.column)
+        let (start, end) = (span.start(), span.end());
+        Loc(file_path, start.line, start.column, end.line, end.column)
     }
+
+    #[inline(always)]
+    pub fn line(&self) -> usize {
+        self.1
+    }
+
+    #[inline(always)]
+    pub fn column(&self) -> usize {
+        self.2
+    }
+
+    #[inline(always)]
+    pub fn end_line(&self) -> usize {
+        self.3
+    }
+
+    #[inline(always)]
+    pub fn path(&self) -> &'a PathBuf {
+        self.0
+    }
+
+    /// The path the item is printed with: relative to `--relative-to` if given and the file is
+    /// under it, absolute if it is not, and as it was walked without `--relative-to`.
+    pub fn display_path(&self) -> Cow::<'a, Path> {
+        let Some(base) = relative_base() else { return Cow::Borrowed(self.0) };
+        let path = absolute(self.0);
+        match path.strip_prefix(base) {
+            Ok(relative) => Cow::Owned(relative.to_owned()),
+            Err(_) => Cow::Owned(path)
+        }
+    }
+
+    /// Byte offsets of the item in `code`, the contents of the file it was found in.
+    ///
+    /// Computed from the lines and columns rather than taken from the span, because the code
+    /// that got parsed is not always the file itself: cached files have their function bodies
+    /// stripped and recovered items are parsed one by one. Lines are 1-based and end with `\n`,
+    /// which is also what `proc_macro2` counts, so files with CRLF or mixed line endings work:
+    /// the `\r` of a `\r\n` is skipped over with the rest of its line, and a lone `\r` is not a
+    /// line break. Columns are 0-based and count chars, not bytes, like `proc_macro2`'s do.
+    pub fn byte_range(&self, code: &str) -> Option::<Range::<usize>> {
+        Some(byte_offset(code, self.1, self.2)?..byte_offset(code, self.3, self.4)?)
+    }
+}
+
+/// The `--relative-to` directory, absolute, resolved once.
+fn relative_base() -> Option::<&'static Path> {
+    static BASE: OnceLock::<Option::<PathBuf>> = OnceLock::new();
+    BASE.get_or_init(|| config().relative_to.as_deref().map(absolute)).as_deref()
+}
+
+/// Canonicalizes `path`, so that symlinks resolve the same way for the base and the files, or
+/// makes it absolute if it can't be.
+fn absolute(path: &Path) -> PathBuf {
+    fs::canonicalize(path).or_else(|_| path::absolute(path)).unwrap_or_else(|_| path.to_owned())
+}
+
+/// Byte offset of `line`:`column` in `code`, a column past the end of its line is clamped to the
+/// end of it, before the `\r\n` or `\n`.
+fn byte_offset(code: &str, line: usize, column: usize) -> Option::<usize> {
+    let mut lines = code.split_inclusive('\n');
+    let line_start = lines.by_ref().take(line.checked_sub(1)?).map(str::len).sum::<usize>();
+    let line = lines.next().unwrap_or_default();
+    let line = line.strip_suffix('\n').map_or(line, |line| line.strip_suffix('\r').unwrap_or(line));
+    let in_line = line.char_indices().nth(column).map_or(line.len(), |(i, _)| i);
+    Some(line_start + in_line)
 }
 
 impl Display for Loc<'_> {
     #[inline(always)]
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{f}:{l}:{c}", f = self.0.display(), l = self.1, c = self.2)
+        write!(f, "{f}:{l}:{c}", f = self.display_path().display(), l = self.1, c = self.2)
     }
 }
 
@@ -28,3 +105,32 @@ impl Debug for Loc<'_> {
         Display::fmt(&self, f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use syn::spanned::Spanned;
+
+    #[test]
+    fn crlf_byte_range() {
+        let path = PathBuf::from("a.rs");
+        let code = "// héllo\r\nfn a() {}\r\n\r\nfn b(\r\n    x: u8\r\n) {}\nfn c() {}\r\n";
+        let file = syn::parse_file(code).unwrap();
+        let items = file.items.iter().map(|item| {
+            let loc = Loc::from_span(&path, &item.span());
+            &code[loc.byte_range(code).unwrap()]
+        }).collect::<Vec::<_>>();
+        assert_eq!(items, ["fn a() {}", "fn b(\r\n    x: u8\r\n) {}", "fn c() {}"]);
+    }
+
+    #[test]
+    fn columns_past_the_end_stop_before_crlf() {
+        let code = "ab\r\ncd\ncé\r\n";
+        assert_eq!(byte_offset(code, 1, 10), Some(2));
+        assert_eq!(byte_offset(code, 2, 10), Some(6));
+        assert_eq!(byte_offset(code, 3, 2), Some(10));
+        assert_eq!(byte_offset(code, 3, 10), Some(10));
+        assert_eq!(byte_offset(code, 0, 0), None);
+    }
+}
@@ -0,0 +1,56 @@
+use syn::{
+    Ident,
+    Token,
+    ItemMacro,
+    parse::{Parse, ParseStream}
+};
+
+use crate::loc::Loc;
+use crate::attrs::Attrs;
+use crate::{skip_tokens, to_static_str};
+
+/// A macro in item position, or a query for one: `macro vec`, `macro lazy_static!` and `macro _`.
+/// Both `macro_rules!` definitions and invocations, like `lazy_static! { .. }`, are indexed, the
+/// latter by the name of the macro invoked, as what they expand to is not indexed.
+#[derive(Debug)]
+pub struct MacroDef {
+    pub name: Option::<&'static str>,
+    /// Whether this is a `macro_rules!` definition rather than an invocation.
+    pub is_def: bool,
+    pub attrs: Attrs
+}
+
+pub type MacroDefs<'a> = Vec::<(Loc<'a>, MacroDef)>;
+
+impl MacroDef {
+    /// Checks whether `other` matches `self` as a query, by name if the query has one.
+    #[inline]
+    pub fn matches(&self, other: &MacroDef) -> bool {
+        self.name.is_none_or(|name| other.name == Some(name))
+    }
+}
+
+impl From::<ItemMacro> for MacroDef {
+    fn from(item: ItemMacro) -> Self {
+        let is_def = item.mac.path.is_ident("macro_rules");
+        let name = if is_def {
+            item.ident.as_ref().map(to_static_str)
+        } else {
+            item.mac.path.segments.last().map(|seg| to_static_str(&seg.ident))
+        };
+        Self {name, is_def, attrs: Attrs::from(item.attrs.as_slice())}
+    }
+}
+
+impl Parse for MacroDef {
+    fn parse(input: ParseStream) -> syn::Result::<Self> {
+        skip_tokens!(input, macro);
+        let name = if input.parse::<Token![_]>().is_ok() {
+            None
+        } else {
+            input.parse::<Ident>().ok().map(|ident| to_static_str(&ident))
+        };
+        skip_tokens!(input, !);
+        Ok(MacroDef {name, is_def: false, attrs: Attrs::default()})
+    }
+}
@@ -1,249 +1,949 @@
 use std::env;
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::io::{self, BufRead, Write};
+use std::time::{Instant, SystemTime};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::process::ExitCode;
-use std::fs::read_to_string;
+use std::fs;
 
-use quote::ToTokens;
 use rayon::prelude::*;
-use proc_macro2::TokenStream;
-use syn::{
-    Pat,
-    File,
-    Type,
-    Token,
-    PatType,
-    PatIdent,
-    ItemImpl,
-    ImplItem,
-    spanned::Spanned,
-    punctuated::Punctuated
-};
-
-mod loc;
-use loc::*;
-mod item;
-use item::*;
-mod fnsig;
-use fnsig::*;
-mod fnarg;
-use fnarg::*;
-mod fields;
-mod dir_rec;
-use dir_rec::*;
-mod enummap;
-mod enumdef;
-use enumdef::*;
-mod structmap;
-use structmap::*;
-mod structdef;
-use structdef::*;
-
-#[macro_export]
-macro_rules! skip_tokens {
-    ($content: expr, $($t: tt), *) => {
-        $(_ = $content.parse::<Token![$t]>();)*
-    };
-}
+use indexmap::IndexMap;
+use syn::Type;
+
+use roogle::ReturnType;
+use roogle::loc::*;
+use roogle::item::*;
+use roogle::fnsig::*;
+use roogle::fields;
+use roogle::dir_rec::*;
+use roogle::enummap::*;
+use roogle::enumdef::*;
+use roogle::structmap::*;
+use roogle::config::*;
+use roogle::normalize::{self, *};
+use roogle::cache::*;
+use roogle::rustdoc;
+use roogle::results::*;
+use roogle::skim;
+use roogle::highlight;
+use roogle::explain;
+use roogle::equiv;
+use roogle::index::*;
+use roogle::attrs;
+use roogle::workspace;
+use roogle::markdown;
+use roogle::template::{Template, Placeholder};
 
-#[inline(always)]
-pub fn to_boxed_string<T: ToTokens>(x: &T) -> Box::<String> {
-    Box::new(x.to_token_stream().to_string().to_lowercase())
+/// With `--invert`, returns the items of `all` that are not in `matches` instead, like `grep -v`.
+/// `all` is every indexed item of the queried kind, so the filters applied before the query,
+/// like `--min-args`, are applied to the inverted matches as well.
+fn invert<'a, 'b>(matches: Matches<'a, 'b>, all: impl Iterator<Item = Match<'a, 'b>>, invert: bool) -> Matches<'a, 'b> {
+    if !invert { return matches }
+    let matched = matches.iter().map(|m| m.loc).collect::<HashSet::<_>>();
+    all.filter(|m| !matched.contains(m.loc)).collect()
 }
 
-#[inline(always)]
-pub fn to_static_str<T: ToTokens>(x: &T) -> &'static str {
-    Box::leak(x.to_token_stream().to_string().to_lowercase().into_boxed_str())
+/// With `--highlight`, renders every match with `render`.
+fn highlight<'a, 'b>(mut matches: Matches<'a, 'b>, render: impl Fn(ItemRef<'a>) -> Option::<String>) -> Matches<'a, 'b> {
+    if config().highlight {
+        matches.iter_mut().for_each(|m| m.highlighted = render(m.item))
+    }
+    matches
 }
 
-pub enum ReturnType {
-    Default,
-    Type(Box::<Type>)
+/// With `--explain`, tells why every match matched with `reason`, or that it does not match the
+/// query for inverted ones.
+fn explained<'a, 'b>(mut matches: Matches<'a, 'b>, inverted: bool, reason: impl Fn(ItemRef<'a>) -> Option::<String>) -> Matches<'a, 'b> {
+    if config().explain {
+        matches.iter_mut().for_each(|m| m.reason = if inverted { Some(explain::INVERTED.to_owned()) } else { reason(m.item) })
+    }
+    matches
 }
 
-impl ToTokens for ReturnType {
-    #[inline]
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        *tokens = self.to_token_stream()
+/// Drops the matches `--no-hidden` and `--no-deprecated` ask to skip, the ones that have no
+/// type parameter bounded by the `--bound` trait and the ones not named as `--name-regex` says.
+fn drop_filtered<'a, 'b>(mut matches: Matches<'a, 'b>) -> Matches<'a, 'b> {
+    let (no_hidden, no_deprecated) = (config().no_hidden, config().no_deprecated);
+    if no_hidden || no_deprecated {
+        matches.retain(|m| !m.item.attrs().filtered_out(no_hidden, no_deprecated))
+    }
+    if !config().kinds.is_empty() || !config().not_kinds.is_empty() {
+        matches.retain(|m| config().kind_matches(m.item.kind()))
+    }
+    if let Some(ref bound) = config().bound {
+        matches.retain(|m| m.item.bounds().contains(&bound.as_str()))
+    }
+    if let Some(ref derive) = config().derives {
+        matches.retain(|m| m.item.derives().contains(&derive.as_str()))
+    }
+    if let Some(ref regex) = config().name_regex {
+        matches.retain(|m| m.item.name().is_some_and(|name| regex.is_match(name)))
+    }
+    if let Some(n) = config().data_variants {
+        matches.retain(|m| !matches!(m.item, ItemRef::Enum(def) if def.data_variants() < n))
+    }
+    if let Some((start, end)) = config().line_range {
+        matches.retain(|m| (start..=end).contains(&m.loc.line()))
     }
+    if let Some(ref name) = config().crate_name {
+        matches.retain(|m| workspace::crate_of(m.loc.path()) == Some(name.as_str()))
+    }
+    if !config().exclude_files.is_empty() {
+        // Compared canonicalized, so that `src/lib.rs` excludes `./src/lib.rs`, as written if that fails.
+        let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        let excluded = config().exclude_files.iter().map(|path| canonical(path)).collect::<HashSet::<_>>();
+        matches.retain(|m| !excluded.contains(&canonical(m.loc.path())))
+    }
+    matches
+}
 
-    fn to_token_stream(&self) -> TokenStream {
-        match self {
-            Self::Default => TokenStream::new(),
-            Self::Type(ty) => if matches!(**ty, Type::Tuple(ref t) if t.elems.is_empty()) {
-                TokenStream::new()                    
-            } else {
-                ty.to_token_stream()
+/// The statistics `--format json-full` and `--machine-summary` report, counted once while walking
+/// the directory, but for `matches`, which is the count of the last printed results.
+struct Stats {
+    files: usize,
+    skipped: usize,
+    matches: AtomicUsize,
+    start: Instant
+}
+
+static STATS: OnceLock::<Stats> = OnceLock::new();
+
+/// Prints the matches of `query`, the query as `highlight::query` renders it, which only
+/// `--format json-full` reports.
+fn print_results(matches: Matches, query: &str) {
+    let mut matches = drop_filtered(matches);
+    matches.sort_unstable_by(|a, b| a.cmp_by(b, config().sort_by));
+    print_sorted(matches, query)
+}
+
+/// Like `print_results`, for matches that are already filtered and sorted.
+fn print_sorted(mut matches: Matches, query: &str) {
+    let hidden = config().max_results_per_file.map(|max| cap_per_file(&mut matches, max)).unwrap_or_default();
+    if let Some(limit) = config().limit { matches.truncate(limit) }
+    if let Some(stats) = STATS.get() { stats.matches.store(matches.len(), Ordering::Relaxed) }
+    let matches = &matches;
+    if config().json_full {
+        print_results_json_full(matches, query)
+    } else if config().json {
+        print_results_json(matches)
+    } else if config().json_lines {
+        print_results_json_lines(matches)
+    } else if config().porcelain {
+        print_results_porcelain(matches)
+    } else if matches.is_empty() {
+        // To stderr, so that stdout is empty when nothing matched.
+        eprintln!("[no results]")
+    } else if config().quickfix {
+        print_results_quickfix(matches)
+    } else if config().picker {
+        print_results_picker(matches)
+    } else if let Some(ref template) = config().template {
+        print_results_template(matches, template)
+    } else if config().by_file {
+        print_results_by_file(matches)
+    } else if config().flat {
+        matches.iter().for_each(|m| {
+            match m.highlighted {
+                Some(ref highlighted) => println!("{loc}  {highlighted}", loc = m.loc),
+                None => println!("{loc}", loc = m.loc)
             }
-        }
+            if let Some(ref reason) = m.reason { println!("    matched: {reason}") }
+        })
+    } else {
+        print_results_grouped(matches)
     }
+    // On stderr along with the other notes of line-based formats, and not to break JSON.
+    hidden.iter().for_each(|(path, count)| {
+        let note = format!("[{path}: {count} more {matches} hidden]", path = path.display(), matches = if *count == 1 { "match" } else { "matches" });
+        if config().is_machine_readable() { eprintln!("{note}") } else { println!("{note}") }
+    })
+}
 
-    #[inline]
-    fn into_token_stream(self) -> TokenStream
-    where
-        Self: Sized
-    {
-        self.to_token_stream()
-    }
+/// Keeps the first `max` of `matches` in each file, for `--max-results-per-file`, and returns how
+/// many were dropped from each file that had more, in the order the files first come in.
+fn cap_per_file<'a>(matches: &mut Matches<'a, '_>, max: usize) -> Vec::<(Cow::<'a, Path>, usize)> {
+    let mut counts = IndexMap::<&PathBuf, (Cow::<Path>, usize), Xx64Hasher>::default();
+    matches.retain(|m| {
+        let (_, count) = counts.entry(m.loc.path()).or_insert_with(|| (m.loc.display_path(), 0));
+        *count += 1;
+        *count <= max
+    });
+    counts.into_values().filter(|(_, count)| *count > max).map(|(path, count)| (path, count - max)).collect()
 }
 
-pub fn signature_get_inputs(inputs: Punctuated::<syn::FnArg, Token![,]>) -> Vec::<FnArg> {
-    inputs.into_iter().filter_map(|fn_arg| {
-        match fn_arg {
-            syn::FnArg::Receiver(..) => None,
-            syn::FnArg::Typed(PatType { pat, ty, .. }) => {
-                let ty = Some(to_boxed_string(&ty));
-                if let Pat::Ident(PatIdent { ident, .. }) = *pat {
-                    let name = Some(to_boxed_string(&ident));
-                    Some(FnArg{name, ty})
-                } else {
-                    let name = None;
-                    Some(FnArg{name, ty})
-                }
-            }
-        }
-    }).collect()
+fn print_results_grouped(matches: &Matches) {
+    print!("{grouped}", grouped = grouped(matches))
 }
 
 #[inline]
-pub fn signature_get_output(output: syn::ReturnType) -> ReturnType {
-    match output {
-        syn::ReturnType::Default => ReturnType::Default,
-        syn::ReturnType::Type(.., ty) => ReturnType::Type(ty),
+fn print_results_json(matches: &Matches) {
+    println!("{json}", json = serde_json::Value::Array(matches_to_json(matches, false)))
+}
+
+/// Prints one JSON object per line and match. Like the other formats, the lines are only printed
+/// once the search is done and the matches sorted, the format saves parsing one big array.
+fn print_results_json_lines(matches: &Matches) {
+    let mut files = HashMap::new();
+    let mut stdout = io::stdout().lock();
+    for m in matches.iter() {
+        if writeln!(stdout, "{json}", json = match_to_json(m, &mut files, false)).is_err() { break }
     }
 }
 
 #[inline]
-pub fn inputs_to_string(inputs: &Vec::<FnArg>) -> String {
-    inputs.iter().map(|FnArg{ty, ..}| quote::quote!(#ty).to_string()).collect()
-}
-
-fn impl_get_fns<'a>(file_path: &'a PathBuf, im: ItemImpl) -> Vec::<(FnSignature, Loc<'a>)> {
-    im.items.into_iter().filter_map(|item| {
-        match item {
-            ImplItem::Fn(f) => {
-                let span = f.span();
-                Some((FnSignature::from(f), Loc::from_span(file_path, &span)))
-            },
-            _ => None
-        }
-    }).collect()
-}
-
-fn parse<'a>(file_path: &'a PathBuf, code: &String) -> syn::Result::<
-    (FnSigs::<'a>, StructDefs::<'a>, EnumDefs::<'a>)
-> {
-    let ast = syn::parse_str::<File>(&code)?;
-    let size = ast.items.len() / 2;
-    let map = ast.items.into_iter().fold((
-        FnSigs::with_capacity(size),
-        StructDefs::with_capacity(size),
-        EnumDefs::with_capacity(size),
-    ), |(mut fnsigs, mut defs, mut edefs), syn_item| {
-        let span = syn_item.span();
-        match syn_item {
-            syn::Item::Fn(f) => {
-                let loc = Loc::from_span(file_path, &span);
-                let sig = FnSignature::from(f.sig);
-                fnsigs.push((loc, sig));
-            }
-            syn::Item::Struct(s) => {
-                let loc = Loc::from_span(file_path, &span);
-                let def = StructDef::from(s);
-                defs.push((loc, def));
-            }
-            syn::Item::Enum(e) => {
-                let loc = Loc::from_span(file_path, &span);
-                let def = EnumDef::from(e);
-                edefs.push((loc, def));
-            }
-            syn::Item::Impl(im) => {
-                impl_get_fns(file_path, im).into_iter().for_each(|(sig, loc)| {
-                    fnsigs.push((loc, sig));
-                })
-            }
-            _ => {}
-        } (fnsigs, defs, edefs)
+fn print_results_porcelain(matches: &Matches) {
+    matches.iter().for_each(|m| println!("{line}", line = porcelain_line(m)))
+}
+
+/// Prints one `path:line:column: kind name` line per match, see `--format quickfix`. Columns are
+/// 1-based there, unlike in the other formats.
+fn print_results_quickfix(matches: &Matches) {
+    matches.iter().for_each(|m| {
+        let (path, line, column) = (m.loc.display_path(), m.loc.line(), m.loc.column() + 1);
+        match m.item.name() {
+            Some(name) => println!("{path}:{line}:{column}: {kind} {name}", path = path.display(), kind = m.item.kind().as_str()),
+            None => println!("{path}:{line}:{column}: {kind}", path = path.display(), kind = m.item.kind().as_str())
+        }
+    })
+}
+
+/// Prints one `signature<TAB>path:line:column` line per match, see `--picker`, the signature
+/// escaped so that the line always has two fields.
+fn print_results_picker(matches: &Matches) {
+    matches.iter().for_each(|m| {
+        let signature = m.item.to_string().replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n");
+        let (path, line, column) = (m.loc.display_path(), m.loc.line(), m.loc.column() + 1);
+        println!("{signature}\t{path}:{line}:{column}", path = path.display())
+    })
+}
+
+/// Prints one line per match rendered from `template`, see `--template`.
+fn print_results_template(matches: &Matches, template: &Template) {
+    matches.iter().for_each(|m| println!("{line}", line = template.render(|placeholder| match placeholder {
+        Placeholder::Path => Some(m.loc.display_path().display().to_string().into()),
+        Placeholder::Line => Some(m.loc.line().to_string().into()),
+        Placeholder::Column => Some(m.loc.column().to_string().into()),
+        Placeholder::Col => Some((m.loc.column() + 1).to_string().into()),
+        Placeholder::Kind => Some(m.item.kind().as_str().into()),
+        Placeholder::Name => m.item.name().map(Into::into),
+        Placeholder::Sig => Some(m.item.to_string().into()),
+        Placeholder::Crate => workspace::crate_of(m.loc.path()).map(Into::into),
+        Placeholder::Reason => m.reason.as_deref().map(Into::into)
+    })))
+}
+
+fn print_results_json_full(matches: &Matches, query: &str) {
+    let stats = STATS.get();
+    let json = serde_json::json!({
+        "query": query,
+        "results": matches_to_json(matches, true),
+        "stats": {
+            "files_searched": stats.map(|s| s.files),
+            "files_skipped": stats.map(|s| s.skipped),
+            "elapsed_ms": stats.map(|s| s.start.elapsed().as_secs_f64() * 1000.0),
+            "matches": matches.len()
+        }
     });
-    Ok(map)
+    println!("{json}")
 }
 
-pub type Results<'a, 'b> = Vec::<&'a Loc<'b>>;
+fn print_results_by_file(matches: &Matches) {
+    let mut counts = HashMap::<_, usize>::new();
+    matches.iter().for_each(|m| *counts.entry(m.loc.display_path()).or_default() += 1);
 
-fn print_results(results: &Results) {
-    if results.is_empty() {
-        println!("[no results]")
-    } else {
-        results.par_iter().for_each(|loc| println!("{loc}"))
+    let mut counts = counts.into_iter().collect::<Vec::<_>>();
+    counts.sort_unstable_by(|(apath, acount), (bpath, bcount)| {
+        bcount.cmp(acount).then_with(|| apath.cmp(bpath))
+    });
+
+    counts.into_iter().for_each(|(path, count)| {
+        println!("{count}  {path}", path = path.display())
+    })
+}
+
+/// How many lines from the top of a file are looked at for a generated code marker.
+const GENERATED_MARKER_LINES: usize = 5;
+
+/// Checks whether the file at `path` is machine-written: either it's under a `target` directory,
+/// where build scripts write their `OUT_DIR` files, or a `// @generated` or `// Code generated`
+/// comment is among its first lines. Only those lines are looked at, the file isn't parsed.
+fn is_generated(path: &Path, code: &str) -> bool {
+    path.components().any(|c| c.as_os_str() == "target") ||
+        code.lines().take(GENERATED_MARKER_LINES).any(|line| {
+            let line = line.trim_start();
+            line.starts_with("//") && (line.contains("@generated") || line.contains("Code generated"))
+        })
+}
+
+/// Walks the current directory, or the source directories of the workspace with `--workspace`,
+/// for the `.rs` files to index. The REPL keeps what it found in the `Index`, and only walks again
+/// on `:rescan`.
+fn walk() -> Result::<Vec::<PathBuf>, String> {
+    let config = config();
+    if let Some(ref file) = config.file {
+        if !file.is_file() { return Err(format!("no file at `{path}`", path = file.display())) }
+        return Ok(vec![file.clone()])
     }
+    let dir = if config.workspace {
+        DirRec::with_roots(workspace::source_roots()?)
+    } else {
+        DirRec::new(".")
+    };
+    Ok(dir.skip_dirs(&config.skip_dirs)
+        .hidden(config.hidden)
+        .filter(|e| e.extension().unwrap_or_default().eq("rs") || (config.extract_markdown && is_markdown(e)))
+        .collect())
+}
+
+#[inline]
+fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "md" || ext == "markdown")
+}
+
+/// Reads the files at `paths`, those `cache` has unchanged from it, and drops the generated ones
+/// unless `--include-generated` is set. Files modified out of `--after` and `--before` are not
+/// even read.
+fn read_sources(paths: &[PathBuf], cache: Option::<&Cache>) -> Vec::<(PathBuf, Option::<SystemTime>, String)> {
+    let config = config();
+    paths.par_iter()
+        .filter_map(|e| {
+            let mtime = modified(e);
+            if !config.mtime_matches(mtime) { return None }
+            if let (Some(cache), Some(mtime)) = (cache, mtime) {
+                if let Some(code) = cache.get(e, mtime) {
+                    return Some((e.clone(), Some(mtime), code.to_owned()))
+                }
+            }
+            // Extracted before caching, the cache has the Rust code of a Markdown file.
+            let code = read_source(e).map(|code| if is_markdown(e) { markdown::rust_blocks(&code) } else { code });
+            code.map(|code| (e.clone(), mtime, code))
+        }).filter(|(e, _, code)| {
+            let skip = !config.include_generated && is_generated(e, code);
+            if skip && config.verbose { eprintln!("[{path}: generated, skipped]", path = e.display()) }
+            !skip
+        }).collect()
+}
+
+fn load_rustdoc() -> Result::<Vec::<rustdoc::Items>, String> {
+    config().rustdoc_json.iter().map(|path| rustdoc::load(path)).collect()
+}
+
+/// Indexes the files at `paths` and the rustdoc JSON files again, for the REPL's `:reload` and
+/// `:rescan`. Returns the index with the number of files it was built from.
+fn reindex(paths: Vec::<PathBuf>) -> Result::<(Index<'static>, usize), String> {
+    let rustdoc_items = load_rustdoc()?;
+    let cache = Cache::load(CACHE_PATH).ok();
+    let contents = read_sources(&paths, cache.as_ref());
+    let files_count = contents.len() + rustdoc_items.len();
+    let mut index = Index::from_sources(contents.into_iter().map(|(file_path, _, code)| (file_path, code)));
+    index.files.extend(rustdoc_items);
+    index.paths = paths;
+    Ok((index, files_count))
+}
+
+fn usage(program: &str) {
+    eprintln!("usage: <{program}> [options] <signature>");
+    eprintln!("{OPTIONS}");
+    eprintln!("{MODIFIERS}")
 }
 
 fn main() -> ExitCode {
-    let args = env::args().collect::<Vec::<_>>();
-    if args.len() < 2 {
-        eprintln!("usage: <{program}> <signature>", program = args[0]);
+    let mut args = env::args();
+    let program = args.next().unwrap_or_default();
+    let config = match Config::parse(args) {
+        Ok(config) => config.init(),
+        Err(e) => {
+            eprintln!("error: {e}");
+            usage(&program);
+            return ExitCode::FAILURE
+        }
+    };
+
+    if let Some(ref path) = config.equiv {
+        if let Err(e) = equiv::load(path) {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE
+        }
+    }
+
+    let query = match config.read_query() {
+        Ok(Some(query)) => query,
+        Ok(None) if config.runs_without_query() => {
+            String::new()
+        }
+        Ok(None) => {
+            usage(&program);
+            return ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("error: could not read query file: {e}");
+            return ExitCode::FAILURE
+        }
+    };
+
+    let start = Instant::now();
+    let cache = if config.build_index {
+        None
+    } else {
+        Cache::load(CACHE_PATH).ok()
+    };
+
+    let rustdoc_items = match load_rustdoc() {
+        Ok(items) => items,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE
+        }
+    };
+
+    let paths = if config.rustdoc_json.is_empty() {
+        match walk() {
+            Ok(paths) => paths,
+            Err(e) => {
+                eprintln!("error: {e}");
+                return ExitCode::FAILURE
+            }
+        }
+    } else {
+        Vec::new()
+    };
+    let contents = read_sources(&paths, cache.as_ref());
+
+    let files_count = contents.len() + config.rustdoc_json.len();
+    if config.build_index {
+        let mut new_cache = Cache::default();
+        contents.iter().for_each(|(file_path, mtime, code)| {
+            let Some(mtime) = mtime else { return };
+            // Cached whole if its bodies can't be told apart, see `skim`.
+            let code = skim::skim(code).unwrap_or_else(|| code.clone());
+            new_cache.insert(file_path.clone(), *mtime, code);
+        });
+        if let Err(e) = new_cache.write(CACHE_PATH) {
+            eprintln!("error: could not write `{CACHE_PATH}`: {e}");
+            return ExitCode::FAILURE
+        }
+    }
+
+    let mut index = Index::from_sources(contents.into_iter().map(|(file_path, _, code)| (file_path, code)));
+    // Files that were not read, generated ones and ones not a single item could be parsed from.
+    let skipped = paths.len() - index.files.len();
+    _ = STATS.set(Stats {files: files_count, skipped, matches: AtomicUsize::new(0), start});
+    index.files.extend(rustdoc_items);
+    index.paths = paths;
+    let defs_count = index.files.iter().map(|(_, defs, ..)| defs.len()).sum::<usize>();
+
+    if config.build_index {
+        let count = index.items_count();
+        println!{
+            "[indexed {count} items from {files} files in {elapsed:.2?}]",
+            files = files_count,
+            elapsed = start.elapsed()
+        };
+        return ExitCode::SUCCESS
+    }
+
+    // Nothing to search is told apart from nothing matching, both go to stderr.
+    if files_count == 0 {
+        eprintln!("[no files searched: no `.rs` files found, or all of them were skipped as hidden, by `--skip-dir`, as generated or by `--after` and `--before`]");
+        return ExitCode::SUCCESS
+    }
+
+    let items = &index.files;
+    if config.types {
+        // Only the types on stdout, for the tools reading them.
+        print_types(items);
+        return ExitCode::SUCCESS
+    } else if let Some(n) = config.top_types {
+        print_top_types(items, n)
+    } else if config.variant.is_some() || config.variant_type.is_some() {
+        search_variants(items)
+    } else if let Some(ref ty) = config.constructors {
+        search_constructors(items, ty)
+    } else if let Some((ref path, line)) = config.similar {
+        if let Err(e) = search_similar(items, path, line) {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE
+        }
+    } else if let (Some(n), true) = (config.data_variants, query.is_empty() && !config.repl) {
+        list_data_variants(items, n)
+    } else if let (Some(derive), true) = (&config.derives, query.is_empty() && !config.repl) {
+        list_derives(items, derive)
+    } else if let (Some(regex), true) = (&config.name_regex, query.is_empty() && !config.repl) {
+        list_named(items, regex.as_str())
+    } else if config.repl {
+        repl(index, files_count);
+        return ExitCode::SUCCESS
+    } else if let Err(e) = search(&query, items, defs_count) {
+        eprintln!("error: {e}");
         return ExitCode::FAILURE
     }
 
-    let dir = DirRec::new(".");
-    let contents = dir.into_iter()
-        .par_bridge()
-        .filter(|e| e.extension().unwrap_or_default().eq("rs"))
-        .filter_map(|e| {
-            read_to_string(&e).ok().map(|code| (e, code))
-        }).collect::<Vec::<_>>();
-
-    let (mut edefs_count, mut defs_count) = (0, 0);
-    let items = contents.iter().flat_map(|(file_path, code)| {
-        if let Ok((fnsigs, defs, edefs)) = parse(file_path, code) {
-            defs_count += defs.len();
-            edefs_count = edefs.len();
-            Some((fnsigs, defs, edefs))
-        } else {
-            None
+    print_searched(files_count);
+    ExitCode::SUCCESS
+}
+
+/// Calls `f` with every type used by function arguments, return types and struct and enum fields,
+/// once per use, of the kinds `--kind` and `--not-kind` let through.
+fn for_each_type(items: &[FileItems], mut f: impl FnMut(&str)) {
+    let kind_matches = |kind| config().kind_matches(kind);
+    items.iter().for_each(|(fnsigs, defs, edefs, ..)| {
+        if kind_matches(Kind::Fn) {
+            fnsigs.iter().for_each(|(_, sig)| {
+                sig.inputs().iter().filter_map(|arg| arg.ty.as_deref()).for_each(|ty| f(ty));
+                if !matches!(sig.output(), ReturnType::Default) { f(&sig.output().to_canonical_string()) }
+            })
         }
+        if kind_matches(Kind::Struct) {
+            defs.iter().flat_map(|(_, def)| def.fields.iter()).filter_map(|f| f.ty).for_each(&mut f)
+        }
+        if kind_matches(Kind::Enum) {
+            edefs.iter()
+                .flat_map(|(_, def)| def.variants.iter().flat_map(|v| v.fields.iter()))
+                .filter_map(|f| f.ty)
+                .for_each(&mut f)
+        }
+    })
+}
+
+/// Runs `--top-types`: tallies every type written in a function signature, a struct field or an
+/// enum field, each occurrence counted, and prints the `n` most common ones.
+fn print_top_types(items: &[FileItems], n: usize) {
+    let mut counts = HashMap::<String, usize>::new();
+    for_each_type(items, |ty| *counts.entry(ty.to_owned()).or_default() += 1);
+
+    let mut counts = counts.into_iter().collect::<Vec::<_>>();
+    counts.sort_unstable_by(|(aty, acount), (bty, bcount)| {
+        bcount.cmp(acount).then_with(|| aty.cmp(bty))
+    });
+    counts.into_iter().take(n).for_each(|(ty, count)| println!("{count}  {ty}"))
+}
+
+/// Prints every distinct type `for_each_type` sees, sorted, one per line.
+fn print_types(items: &[FileItems]) {
+    let mut types = BTreeSet::<String>::new();
+    for_each_type(items, |ty| if !types.contains(ty) { types.insert(ty.to_owned()); });
+    let mut stdout = io::stdout().lock();
+    types.iter().for_each(|ty| _ = writeln!(stdout, "{ty}"))
+}
+
+/// Runs `--constructors`, listing the constructors of `ty`, see `FnSignature::is_constructor_of`,
+/// with their signatures, those taking the fewest arguments first.
+fn search_constructors(items: &[FileItems], ty: &str) {
+    let Ok(ty) = syn::parse_str::<Type>(ty).map(type_to_string) else { return };
+    // The impl blocks name the type as it's imported, so only its last segment is compared.
+    let ty = ty.split('<').next().unwrap_or_default().rsplit("::").next().unwrap_or_default();
+    let matches = items.iter()
+        .flat_map(|(fnsigs, ..)| fnsigs)
+        .filter(|(_, sig)| sig.is_constructor_of(ty))
+        .map(|(loc, sig)| Match {highlighted: Some(sig.to_string()), ..Match::new(loc, ItemRef::Fn(sig))})
+        .collect();
+    let mut matches = drop_filtered(matches);
+    matches.sort_unstable_by(|a, b| match (a.item, b.item) {
+        (ItemRef::Fn(a_sig), ItemRef::Fn(b_sig)) => a_sig.inputs().len().cmp(&b_sig.inputs().len()),
+        _ => std::cmp::Ordering::Equal
+    }.then_with(|| a.cmp_by(b, config().sort_by)));
+    print_sorted(matches, &format!("--constructors {ty}"))
+}
+
+/// Runs `--similar`: finds the function, struct or enum starting at `line` of the file at `path`,
+/// or else the innermost one the line is in, and lists the items of its kind most like it first,
+/// see `similarity`.
+fn search_similar(items: &[FileItems], path: &Path, line: usize) -> Result::<(), String> {
+    let target = fs::canonicalize(path).map_err(|e| format!("could not read `{path}`: {e}", path = path.display()))?;
+    let file = items.iter().map(similar_candidates).find(|file| {
+        file.first().is_some_and(|(loc, _)| fs::canonicalize(loc.path()).is_ok_and(|path| path == target))
+    }).ok_or_else(|| format!("no function, struct or enum found in `{path}`", path = path.display()))?;
+    let (loc, item) = file.iter().find(|(loc, _)| loc.line() == line).or_else(|| {
+        file.iter().filter(|(loc, _)| loc.line() <= line && line <= loc.end_line()).max_by_key(|(loc, _)| (loc.line(), loc.column()))
+    }).copied().ok_or_else(|| format!("no function, struct or enum at `{path}:{line}`", path = path.display()))?;
+
+    let mut matches = items.iter().flat_map(similar_candidates)
+        .filter(|(other_loc, other)| *other_loc != loc && other.kind() == item.kind())
+        .filter_map(|(other_loc, other)| similarity(item, other).map(|(score, reason)| {
+            let reason = config().explain.then_some(reason);
+            (score, Match {reason, ..Match::new(other_loc, other)})
+        }))
+        .collect::<Vec::<_>>();
+    matches.retain(|(_, m)| !m.item.attrs().filtered_out(config().no_hidden, config().no_deprecated));
+    matches.sort_unstable_by(|(a_score, a), (b_score, b)| b_score.cmp(a_score).then_with(|| a.cmp_by(b, config().sort_by)));
+    if !config().is_machine_readable() { println!("[similar to {item} at {loc}]") }
+    print_sorted(matches.into_iter().map(|(_, m)| m).collect(), &format!("--similar {path}:{line}", path = path.display()));
+    Ok(())
+}
+
+/// The score of an item sharing the `parts` of another one with it, counted, and why, if it
+/// shares any, like `shares 2 variant names, 1 field type`.
+fn shared_parts(parts: &[(usize, &str)]) -> Option::<(usize, String)> {
+    let score = parts.iter().map(|(count, _)| count).sum::<usize>();
+    let reasons = parts.iter().filter(|(count, _)| *count > 0).map(|(count, what)| {
+        format!("{count} {what}{s}", s = if *count == 1 { "" } else { "s" })
     }).collect::<Vec::<_>>();
+    (score > 0).then(|| (score, format!("shares {reasons}", reasons = reasons.join(", "))))
+}
+
+/// The functions, structs and enums of a file, the items `--similar` compares.
+fn similar_candidates<'a, 'b>((fnsigs, defs, edefs, ..): &'a FileItems<'b>) -> Vec::<(&'a Loc<'b>, ItemRef<'a>)> {
+    let fns = fnsigs.iter().map(|(loc, sig)| (loc, ItemRef::Fn(sig)));
+    let structs = defs.iter().map(|(loc, def)| (loc, ItemRef::Struct(def)));
+    fns.chain(structs).chain(edefs.iter().map(|(loc, def)| (loc, ItemRef::Enum(def)))).collect()
+}
+
+/// How much `other` is like `item`, the higher the more, with why, if they are alike at all:
+/// a function with the same signature scores the most, then one with the same one ignoring more
+/// and more of it, see `Loosening`, and a struct or an enum scores the number of field types,
+/// and of variant and field names, it has in common with `item`.
+fn similarity(item: ItemRef, other: ItemRef) -> Option::<(usize, String)> {
+    let shared = |names: &[Option::<&str>], others: &[Option::<&str>]| {
+        names.iter().flatten().filter(|name| others.contains(&Some(name))).count()
+    };
+    match (item, other) {
+        (ItemRef::Fn(sig), ItemRef::Fn(other)) => {
+            if sig.matches(other) { return Some((Loosening::CASCADE.len() + 1, "same signature".to_owned())) }
+            Loosening::CASCADE.iter().position(|loosening| sig.matches_loosely(other, *loosening)).map(|i| {
+                (Loosening::CASCADE.len() - i, format!("same signature ignoring {}", Loosening::CASCADE[i].ignored()))
+            })
+        }
+        (ItemRef::Struct(def), ItemRef::Struct(other)) => {
+            let types = shared(&def.fields.iter().map(|f| f.ty).collect::<Vec::<_>>(), &other.fields.iter().map(|f| f.ty).collect::<Vec::<_>>());
+            let names = shared(&def.fields.iter().map(|f| f.name).collect::<Vec::<_>>(), &other.fields.iter().map(|f| f.name).collect::<Vec::<_>>());
+            shared_parts(&[(types, "field type"), (names, "field name")])
+        }
+        (ItemRef::Enum(def), ItemRef::Enum(other)) => {
+            let fields = |def: &EnumDef, part: fn(&fields::Field) -> Option::<&'static str>| {
+                def.variants.iter().flat_map(|v| v.fields.iter().map(part)).collect::<Vec::<_>>()
+            };
+            let types = shared(&fields(def, |f| f.ty), &fields(other, |f| f.ty));
+            let names = shared(&fields(def, |f| f.name), &fields(other, |f| f.name));
+            let variants = shared(&def.variants.iter().map(|v| v.name).collect::<Vec::<_>>(), &other.variants.iter().map(|v| v.name).collect::<Vec::<_>>());
+            shared_parts(&[(variants, "variant name"), (types, "field type"), (names, "field name")])
+        }
+        _ => None
+    }
+}
+
+/// Runs `--data-variants` without a query, listing the enums it lets through.
+fn list_data_variants(items: &[FileItems], n: usize) {
+    let matches = items.iter()
+        .flat_map(|(_, _, edefs, ..)| edefs)
+        .map(|(loc, def)| Match::new(loc, ItemRef::Enum(def)))
+        .collect();
+    print_results(matches, &format!("--data-variants {n}"))
+}
 
-    let query_item = syn::parse_str::<Item>(&args[1]).unwrap();
-    match query_item {
+/// Lists the structs and enums deriving `derive`, for `--derives` without a query.
+fn list_derives(items: &[FileItems], derive: &str) {
+    let matches = items.iter().flat_map(|(_, defs, edefs, ..)| {
+        let structs = defs.iter().map(|(loc, def)| Match::new(loc, ItemRef::Struct(def)));
+        structs.chain(edefs.iter().map(|(loc, def)| Match::new(loc, ItemRef::Enum(def))))
+    }).collect();
+    print_results(matches, &format!("--derives {derive}"))
+}
+
+/// Lists the items of every kind named as `regex` says, for `--name-regex` without a query.
+fn list_named(items: &[FileItems], regex: &str) {
+    let matches = items.iter().flat_map(|(fnsigs, defs, edefs, cdefs, tdefs, mdefs, tydefs)| {
+        let fns = fnsigs.iter().map(|(loc, sig)| Match::new(loc, ItemRef::Fn(sig)));
+        let structs = defs.iter().map(|(loc, def)| Match::new(loc, ItemRef::Struct(def)));
+        let enums = edefs.iter().map(|(loc, def)| Match::new(loc, ItemRef::Enum(def)));
+        let consts = cdefs.iter().map(|(loc, def)| Match::new(loc, ItemRef::Const(def)));
+        let traits = tdefs.iter().map(|(loc, def)| Match::new(loc, ItemRef::Trait(def)));
+        let macros = mdefs.iter().map(|(loc, def)| Match::new(loc, ItemRef::Macro(def)));
+        fns.chain(structs).chain(enums).chain(consts).chain(traits).chain(macros).chain(tydefs.iter().map(|(loc, def)| Match::new(loc, ItemRef::Type(def))))
+    }).collect();
+    print_results(matches, &format!("--name-regex {regex}"))
+}
+
+/// Runs `--variant` or `--variant-type`, which search enums without a query.
+fn search_variants(items: &[FileItems]) {
+    let edefs = items.iter().flat_map(|(_, _, edefs, ..)| edefs).collect::<Vec::<_>>();
+    let map = EnumMap::new(edefs.iter().copied());
+    let defs = edefs.iter().map(|(loc, def)| (loc, def)).collect::<HashMap::<_, _>>();
+    let locs = if let Some(ref variant) = config().variant {
+        map.variant_map.get(variant.to_lowercase().as_str())
+    } else {
+        // The type was already checked to parse by `Config::parse`.
+        let ty = config().variant_type.as_deref().and_then(|ty| syn::parse_str::<Type>(ty).ok()).map(type_to_string);
+        ty.and_then(|ty| map.type_map.get(ty.as_str()))
+    };
+    // An enum with several variants holding the same type is in `type_map` once per variant.
+    let mut seen = HashSet::new();
+    let matches = locs.into_iter().flatten()
+        .filter(|loc| seen.insert(*loc))
+        .map(|loc| Match::new(loc, ItemRef::Enum(defs[loc])))
+        .collect();
+    let all = edefs.iter().map(|(loc, def)| Match::new(loc, ItemRef::Enum(def)));
+    let query = match config().variant {
+        Some(ref variant) => format!("--variant {variant}"),
+        None => format!("--variant-type {ty}", ty = config().variant_type.as_deref().unwrap_or_default())
+    };
+    let reason = match config().variant {
+        Some(ref variant) => format!("has the variant `{variant}`", variant = variant.to_lowercase()),
+        None => format!("has a variant holding `{ty}`", ty = config().variant_type.as_deref().unwrap_or_default())
+    };
+    print_results(explained(invert(matches, all, config().invert), config().invert, |_| Some(reason.clone())), &query);
+}
+
+fn search(query: &str, items: &[FileItems], defs_count: usize) -> Result::<(), String> {
+    let Query {modifiers, item} = parse_query(query).map_err(|e| format!("invalid query: {e}"))?;
+    let query = &highlight::query(&item);
+    let (inverted, suggest) = (config().invert || modifiers.invert, config().suggest || modifiers.suggest);
+    match item {
         Item::StructDef(def) => {
-            let maps = items.iter().map(|(.., defs, _)| {
-                let mut map = StructDefMap::new(defs_count);
-                defs.into_iter().for_each(|(loc, def)| map.insert(def, loc));
-                map.finalize();
-                map
-            }).collect::<Vec::<_>>();
-            let Some(iter) = def.fields.par_iter() else { return ExitCode::SUCCESS };
-            let results = iter.filter_map(|f| {
-                if let Some(name) = f.name {
-                    Some(maps.iter().flat_map(|map| map.find_names(name, def.is_tup)).collect::<Vec::<_>>())
-                } else if let Some(ty) = f.ty {
-                    Some(maps.iter().flat_map(|map| map.find_types(ty, def.is_tup)).collect::<Vec::<_>>())
-                } else {
-                    None
-                }
-            }).flatten().collect::<Vec::<_>>();
-            print_results(&results);
+            if config().ordered_fields && def.fields.iter().any(|f| f.name.is_none()) {
+                return Err("`--ordered-fields` needs every field of the query to be named".to_owned())
+            }
+            // One map for every file, so each struct is found once however many query fields it has.
+            let mut map = StructDefMap::new(defs_count);
+            items.iter().flat_map(|(_, defs, ..)| defs).for_each(|(loc, def)| map.insert(def, loc));
+            let matches = map.search(&def).into_iter().map(|(loc, found)| Match::new(loc, ItemRef::Struct(found))).collect();
+            let all = map.defs().map(|(loc, def)| Match::new(loc, ItemRef::Struct(def)));
+            let matches = explained(invert(matches, all, inverted), inverted, |item| match item {
+                ItemRef::Struct(found) => Some(explain::struct_def(&def, found)),
+                _ => None
+            });
+            print_results(highlight(matches, |item| match item {
+                ItemRef::Struct(found) => Some(highlight::struct_def(&def, found)),
+                _ => None
+            }), query);
         }
         Item::EnumDef(edef) => {
-            let edefs = items.into_iter().flat_map(|(.., edefs)| edefs).collect::<Vec::<_>>();
-            EnumDef::search_enum_def(&edef, &edefs);
+            let edefs = items.iter().flat_map(|(_, _, edefs, ..)| edefs).collect::<Vec::<_>>();
+            let results = EnumDef::search_enum_def(&edef, &edefs);
+            let defs = edefs.iter().map(|(loc, def)| (loc, def)).collect::<HashMap::<_, _>>();
+            let matches = results.into_iter()
+                .filter(|loc| !config().strict_variants || defs[loc].has_variants_of(&edef))
+                .map(|loc| Match::new(loc, ItemRef::Enum(defs[loc])))
+                .collect();
+            let all = edefs.iter().map(|(loc, def)| Match::new(loc, ItemRef::Enum(def)));
+            print_results(explained(invert(matches, all, inverted), inverted, |item| match item {
+                ItemRef::Enum(found) => Some(explain::enum_def(&edef, found)),
+                _ => None
+            }), query);
         },
+        Item::ConstDef(cdef) => {
+            let cdefs = items.iter().flat_map(|(_, _, _, cdefs, ..)| cdefs);
+            let matches = cdefs.clone()
+                .filter(|(_, def)| cdef.matches(def))
+                .map(|(loc, def)| Match::new(loc, ItemRef::Const(def)))
+                .collect();
+            let all = cdefs.map(|(loc, def)| Match::new(loc, ItemRef::Const(def)));
+            print_results(explained(invert(matches, all, inverted), inverted, |_| Some(explain::const_def(&cdef))), query);
+        }
+        Item::TraitDef(tdef) => {
+            let tdefs = items.iter().flat_map(|(_, _, _, _, tdefs, ..)| tdefs);
+            let matches = tdefs.clone()
+                .filter(|(_, def)| tdef.matches(def))
+                .map(|(loc, def)| Match::new(loc, ItemRef::Trait(def)))
+                .collect();
+            let all = tdefs.map(|(loc, def)| Match::new(loc, ItemRef::Trait(def)));
+            print_results(explained(invert(matches, all, inverted), inverted, |_| Some(explain::trait_def(&tdef))), query);
+        }
+        Item::MacroDef(mdef) => {
+            let mdefs = items.iter().flat_map(|(_, _, _, _, _, mdefs, _)| mdefs);
+            let matches = mdefs.clone()
+                .filter(|(_, def)| mdef.matches(def))
+                .map(|(loc, def)| Match::new(loc, ItemRef::Macro(def)))
+                .collect();
+            let all = mdefs.map(|(loc, def)| Match::new(loc, ItemRef::Macro(def)));
+            print_results(explained(invert(matches, all, inverted), inverted, |item| match item {
+                ItemRef::Macro(found) => Some(explain::macro_def(&mdef, found)),
+                _ => None
+            }), query);
+        }
+        Item::TypeDef(tydef) => {
+            let tydefs = items.iter().flat_map(|(.., tydefs)| tydefs);
+            let matches = tydefs.clone()
+                .filter(|(_, def)| tydef.matches(def))
+                .map(|(loc, def)| Match::new(loc, ItemRef::Type(def)))
+                .collect();
+            let all = tydefs.map(|(loc, def)| Match::new(loc, ItemRef::Type(def)));
+            print_results(explained(invert(matches, all, inverted), inverted, |_| Some(explain::type_def(&tydef))), query);
+        }
+        Item::ImplQuery(iquery) => {
+            let fnsigs = items.iter().flat_map(|(fnsigs, ..)| fnsigs);
+            // The structs and enums deriving a trait implement it too, see `ImplQuery::derived_by`.
+            let derivers = items.iter().flat_map(|(_, defs, edefs, ..)| {
+                let structs = defs.iter().map(|(loc, def)| (loc, ItemRef::Struct(def)));
+                structs.chain(edefs.iter().map(|(loc, def)| (loc, ItemRef::Enum(def))))
+            }).filter(|(_, item)| item.derives().iter().any(|derive| attrs::DERIVABLE.contains(derive)));
+            let matches = iquery.search(fnsigs.clone()).into_iter()
+                .map(|(loc, sig)| Match::new(loc, ItemRef::Fn(sig)))
+                .chain(derivers.clone().filter(|(_, item)| iquery.derived_by(item.derives())).map(|(loc, item)| Match::new(loc, item)))
+                .collect();
+            let all = fnsigs.filter(|(_, sig)| sig.impl_trait().is_some()).map(|(loc, sig)| Match::new(loc, ItemRef::Fn(sig)))
+                .chain(derivers.map(|(loc, item)| Match::new(loc, item)));
+            let matches = explained(invert(matches, all, inverted), inverted, |item| match item {
+                ItemRef::Fn(sig) => Some(explain::impl_method(&iquery, sig)),
+                _ => Some(explain::derived_impl(&iquery))
+            });
+            print_results(highlight(matches, |item| match item {
+                ItemRef::Fn(sig) => Some(match iquery.matched_by(sig) {
+                    Some(method) => highlight::fn_signature(method, sig),
+                    None => sig.to_string()
+                }),
+                _ => None
+            }), query);
+        }
         Item::FnSignature(fnsig) => {
-            let maps = items.into_iter().map(|(fnsigs, ..)| {
-                fnsigs.into_iter().map(|(loc, fnsig)| (fnsig, loc)).collect::<FnSigMap>()
+            let fnsigs = items.iter().map(|(fnsigs, ..)| {
+                fnsigs.iter().filter(|(_, sig)| sig.passes_filters()).collect::<Vec::<_>>()
             }).collect::<Vec::<_>>();
-            let results = maps.iter().filter_map(|map| map.get(&fnsig)).collect::<Vec::<_>>();
-            print_results(&results);
+            let matches = if !fnsig.is_exact() {
+                fnsigs.iter().flatten()
+                    .filter(|(_, sig)| fnsig.matches(sig))
+                    .map(|(loc, sig)| Match::new(loc, ItemRef::Fn(sig)))
+                    .collect::<Matches>()
+            } else {
+                // Generic signatures match more than what they compare equal to, so they are
+                // matched one by one rather than looked up.
+                let maps = fnsigs.iter().map(|fnsigs| {
+                    let mut map = FnSigMap::with_capacity(fnsigs.len());
+                    fnsigs.iter()
+                        .filter(|(_, sig)| sig.generics().is_empty())
+                        .for_each(|(loc, sig)| map.entry(sig).or_default().push((loc, sig)));
+                    map
+                }).collect::<Vec::<_>>();
+                let generic = fnsigs.iter().flatten().filter(|(_, sig)| !sig.generics().is_empty() && fnsig.matches(sig));
+                maps.iter()
+                    .filter_map(|map| map.get(&fnsig))
+                    .flatten()
+                    .copied()
+                    .chain(generic.map(|(loc, sig)| (loc, sig)))
+                    .map(|(loc, sig)| Match::new(loc, ItemRef::Fn(sig)))
+                    .collect()
+            };
+            let all = fnsigs.iter().flatten().map(|(loc, sig)| Match::new(loc, ItemRef::Fn(sig)));
+            let matches = explained(invert(matches, all, inverted), inverted, |item| match item {
+                ItemRef::Fn(sig) => Some(explain::fn_signature(&fnsig, sig)),
+                _ => None
+            });
+            let matches = highlight(matches, |item| match item {
+                ItemRef::Fn(sig) => Some(highlight::fn_signature(&fnsig, sig)),
+                _ => None
+            });
+            let loosened = if matches.is_empty() && suggest && !inverted {
+                Loosening::CASCADE.into_iter().find_map(|loosening| {
+                    let matches = fnsigs.iter().flatten()
+                        .filter(|(_, sig)| fnsig.matches_loosely(sig, loosening))
+                        .map(|(loc, sig)| Match::new(loc, ItemRef::Fn(sig)))
+                        .collect::<Matches>();
+                    let matches = explained(drop_filtered(matches), false, |_| Some(format!("matched ignoring {}", loosening.ignored())));
+                    let matches = highlight(matches, |item| match item {
+                        ItemRef::Fn(sig) => Some(highlight::fn_signature(&fnsig, sig)),
+                        _ => None
+                    });
+                    (!matches.is_empty()).then_some((loosening, matches))
+                })
+            } else {
+                None
+            };
+            if let Some((loosening, matches)) = loosened {
+                if !config().is_machine_readable() {
+                    println!{
+                        "[no exact match; found {count} ignoring {ignored}]",
+                        count = matches.len(),
+                        ignored = loosening.ignored()
+                    }
+                }
+                print_results(matches, query)
+            } else {
+                print_results(matches, query)
+            }
         }
-    };
+    }
+    Ok(())
+}
 
-    println!{
+fn print_searched(files_count: usize) {
+    let searched = format!{
         "[searched in {count} {files}]",
-        count = contents.len(),
-        files = if contents.len() == 1 { "file" } else { "files" }
+        count = files_count,
+        files = if files_count == 1 { "file" } else { "files" }
     };
+    // Keep the JSON output parsable as a whole, line-based output can have the summary on stderr.
+    if config().json_lines || config().porcelain || config().quickfix || config().picker || config().template.is_some() {
+        eprintln!("{searched}")
+    } else if !config().is_json() {
+        println!("{searched}")
+    }
+    if let (true, Some(stats)) = (config().machine_summary, STATS.get()) {
+        eprintln!{
+            "roogle: files={files} skipped={skipped} matches={matches} elapsed_ms={elapsed}",
+            files = stats.files,
+            skipped = stats.skipped,
+            matches = stats.matches.load(Ordering::Relaxed),
+            elapsed = stats.start.elapsed().as_millis()
+        }
+    }
+    let given_up = normalize::take_given_up();
+    if config().verbose && given_up > 0 {
+        eprintln!("[{given_up} type comparisons given up past the depth limit of {limit}]", limit = config().type_depth_limit())
+    }
+}
 
-    ExitCode::SUCCESS
+/// Reads queries from stdin until EOF or `:quit`, searching the index built once at startup.
+/// The prompt goes to stderr, so that stdout only has the results, each followed by an empty line.
+fn repl(mut index: Index<'static>, mut files_count: usize) {
+    let mut stdin = io::stdin().lock();
+    let mut line = String::new();
+    let defs_count = |index: &Index| index.files.iter().map(|(_, defs, ..)| defs.len()).sum::<usize>();
+    loop {
+        eprint!("roogle> ");
+        line.clear();
+        if !matches!(stdin.read_line(&mut line), Ok(n) if n > 0) { break }
+        let items = &index.files;
+        match line.trim() {
+            "" => continue,
+            ":quit" | ":q" => break,
+            ":stats" => {
+                let counts = items.iter().fold([0; 7], |[fns, structs, enums, consts, traits, macros, types], (fnsigs, defs, edefs, cdefs, tdefs, mdefs, tydefs)| {
+                    [fns + fnsigs.len(), structs + defs.len(), enums + edefs.len(), consts + cdefs.len(), traits + tdefs.len(), macros + mdefs.len(), types + tydefs.len()]
+                });
+                println!{
+                    "[{fns} functions, {structs} structs, {enums} enums, {consts} constants, {traits} traits, {macros} macros, {types} types from {files_count} files]",
+                    fns = counts[0],
+                    structs = counts[1],
+                    enums = counts[2],
+                    consts = counts[3],
+                    traits = counts[4],
+                    macros = counts[5],
+                    types = counts[6]
+                }
+            }
+            // `:reload` reads the files found at startup again, `:rescan` walks the directory again
+            // first, for files that were added or removed.
+            command @ (":reload" | ":rescan") => {
+                let start = Instant::now();
+                let paths = if command == ":rescan" && config().rustdoc_json.is_empty() {
+                    walk()
+                } else {
+                    Ok(index.paths.clone())
+                };
+                match paths.and_then(reindex) {
+                    Ok((new_index, count)) => {
+                        (index, files_count) = (new_index, count);
+                        println!{
+                            "[indexed {items} items from {files_count} files in {elapsed:.2?}]",
+                            items = index.items_count(),
+                            elapsed = start.elapsed()
+                        }
+                    }
+                    Err(e) => eprintln!("error: {e}")
+                }
+            }
+            command if command.starts_with(':') => {
+                eprintln!("error: unknown command: `{command}`, expected `:stats`, `:reload`, `:rescan` or `:quit`")
+            }
+            query => match search(query, items, defs_count(&index)) {
+                Ok(()) => print_searched(files_count),
+                Err(e) => eprintln!("error: {e}")
+            }
+        }
+        println!()
+    }
 }
 
 /* TODO:
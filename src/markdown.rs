@@ -0,0 +1,49 @@
+/// Checks whether the info string of a fenced code block marks it as Rust, like `rust`,
+/// `rust,ignore` or `rs`.
+#[inline]
+fn is_rust_info(info: &str) -> bool {
+    let lang = info.split([',', ' ']).next().unwrap_or_default();
+    lang == "rust" || lang == "rs"
+}
+
+/// Checks whether `line` closes the code block opened by `open`: it's made of the same char only,
+/// at least as many of them.
+#[inline]
+fn is_closing_fence(line: &str, open: &str) -> bool {
+    line.len() >= open.len() && line.chars().all(|c| open.starts_with(c))
+}
+
+/// Turns the Markdown `md` into Rust code made of its fenced Rust code blocks, for
+/// `--extract-markdown`. Every other line is blanked out rather than removed, so the lines and
+/// columns of the items parsed from the blocks are the ones they have in the Markdown file.
+pub fn rust_blocks(md: &str) -> String {
+    let mut code = String::with_capacity(md.len());
+    // The fence of the block the line is in, and whether that block is Rust.
+    let mut fence = Option::<(&str, bool)>::None;
+    for line in md.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let fence_len = |c: char| trimmed.chars().take_while(|&t| t == c).count();
+        let in_rust = match fence {
+            Some((open, _)) if is_closing_fence(trimmed.trim_end(), open) => {
+                fence = None;
+                false
+            }
+            Some((_, is_rust)) => is_rust,
+            None => {
+                let len = match (fence_len('`'), fence_len('~')) {
+                    (len, _) if len >= 3 => len,
+                    (_, len) if len >= 3 => len,
+                    _ => 0
+                };
+                if len > 0 { fence = Some((&trimmed[..len], is_rust_info(trimmed[len..].trim()))) }
+                false
+            }
+        };
+        if in_rust {
+            code.push_str(line)
+        } else if line.ends_with('\n') {
+            code.push('\n')
+        }
+    }
+    code
+}
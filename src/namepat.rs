@@ -0,0 +1,106 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+
+/// What `\*` stands for in a query while it's tokenized, a backslash being no Rust token: a part
+/// of an identifier, which the name of a function query then turns back into a star.
+const ESCAPED_STAR: &str = "__roogle_escaped_star__";
+
+/// Replaces the escaped stars of `query`, see `ESCAPED_STAR`.
+#[inline]
+pub fn escape_stars(query: &str) -> Cow::<'_, str> {
+    if query.contains("\\*") { Cow::Owned(query.replace("\\*", ESCAPED_STAR)) } else { Cow::Borrowed(query) }
+}
+
+/// The name of a function query with its escaped stars, see `escape_stars`, back to stars.
+#[inline]
+pub fn unescape_stars(name: String) -> String {
+    if name.contains(ESCAPED_STAR) { name.replace(ESCAPED_STAR, "*") } else { name }
+}
+
+/// A name in a query with `*` on either side: `connect*`, `*connect` or `*connect*`. A star
+/// escaped as `\*` is part of the name instead, so `conn\**` is the prefix `conn*`.
+#[derive(Debug)]
+pub enum NamePattern {
+    Prefix(String),
+    Suffix(String),
+    Contains(String)
+}
+
+impl NamePattern {
+    /// Returns `None` for a name without stars, those are not used for filtering.
+    pub fn new(name: &str, leading_star: bool, trailing_star: bool) -> Option::<Self> {
+        let name = name.to_lowercase();
+        match (leading_star, trailing_star) {
+            (false, false) => None,
+            (false, true) => Some(Self::Prefix(name)),
+            (true, false) => Some(Self::Suffix(name)),
+            (true, true) => Some(Self::Contains(name))
+        }
+    }
+
+    /// `name` is expected to be lowercased, like every indexed name is.
+    #[inline]
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Prefix(prefix) => name.starts_with(prefix.as_str()),
+            Self::Suffix(suffix) => name.ends_with(suffix.as_str()),
+            Self::Contains(part) => name.contains(part.as_str())
+        }
+    }
+}
+
+impl Display for NamePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Prefix(prefix) => write!(f, "{}*", prefix.replace('*', "\\*")),
+            Self::Suffix(suffix) => write!(f, "*{}", suffix.replace('*', "\\*")),
+            Self::Contains(part) => write!(f, "*{}*", part.replace('*', "\\*"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::index::Index;
+    use crate::item::{Item, parse_query};
+
+    fn parse_fn(query: &str) -> crate::fnsig::FnSignature {
+        match parse_query(query).unwrap().item {
+            Item::FnSignature(sig) => sig,
+            item => panic!("expected a function query, got {item:?}")
+        }
+    }
+
+    #[test]
+    fn patterns() {
+        let prefix = NamePattern::new("Connect", false, true).unwrap();
+        assert!(prefix.matches("connect_timeout") && !prefix.matches("reconnect"));
+        let suffix = NamePattern::new("connect", true, false).unwrap();
+        assert!(suffix.matches("reconnect") && !suffix.matches("connect_timeout"));
+        let contains = NamePattern::new("connect", true, true).unwrap();
+        assert!(contains.matches("try_connect_all") && !contains.matches("conn"));
+        assert!(NamePattern::new("connect", false, false).is_none());
+        assert_eq!(contains.to_string(), "*connect*");
+    }
+
+    #[test]
+    fn escaped_stars() {
+        assert_eq!(escape_stars("fn connect*(u8)"), "fn connect*(u8)");
+        assert_eq!(parse_fn(r"fn \*connect(u8)").name(), Some("*connect"));
+        assert_eq!(parse_fn(r"fn conn\*ect").name(), Some("conn*ect"));
+        let prefix = NamePattern::new(parse_fn(r"fn conn\**").name().unwrap(), false, true).unwrap();
+        assert!(prefix.matches("conn*ect") && !prefix.matches("connect"));
+        assert_eq!(prefix.to_string(), r"conn\**");
+        assert_eq!(parse_fn(r"fn *\*connect*(SocketAddr) -> _").name(), Some("*connect"));
+    }
+
+    #[test]
+    fn escaped_stars_match_no_identifier() {
+        let index = Index::from_sources([("a.rs".into(), "fn connect() {}".to_owned())]);
+        let connect = &index.files[0].0[0].1;
+        assert!(parse_fn("fn conn*").matches(connect));
+        assert!(!parse_fn(r"fn conn\**").matches(connect));
+    }
+}
@@ -0,0 +1,620 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use quote::ToTokens;
+use syn::{
+    Type,
+    TypePath,
+    TypeGroup,
+    TypeParen,
+    TypeSlice,
+    TypeArray,
+    TypeReference,
+    PathArguments,
+    GenericArgument,
+    TypeImplTrait,
+    TypeTraitObject,
+    TypeParamBound,
+    Generics,
+    ReturnType,
+    WherePredicate,
+    token::Dyn,
+    punctuated::{Pair, Punctuated}
+};
+
+use crate::equiv;
+use crate::to_static_str;
+use crate::config::config;
+
+const SMART_POINTERS: &[&str] = &["Box", "Rc", "Arc"];
+
+/// The std items commonly written with their full path, by their module under `std`, `core` or
+/// `alloc` and their name. Those paths are shortened to the bare name, so `std::string::String`
+/// and `String` are the same type, while `std::io::Result` stays apart from `Result`.
+const STD_PATHS: &[(&str, &str)] = &[
+    ("string", "String"),
+    ("vec", "Vec"),
+    ("boxed", "Box"),
+    ("rc", "Rc"),
+    ("sync", "Arc"),
+    ("sync", "Mutex"),
+    ("sync", "RwLock"),
+    ("cell", "Cell"),
+    ("cell", "RefCell"),
+    ("borrow", "Cow"),
+    ("option", "Option"),
+    ("result", "Result"),
+    ("collections", "HashMap"),
+    ("collections", "HashSet"),
+    ("collections", "BTreeMap"),
+    ("collections", "BTreeSet"),
+    ("collections", "VecDeque"),
+    ("collections", "BinaryHeap"),
+    ("collections::hash_map", "HashMap"),
+    ("collections::hash_set", "HashSet"),
+    ("path", "Path"),
+    ("path", "PathBuf"),
+    ("ffi", "OsStr"),
+    ("ffi", "OsString"),
+    ("ffi", "CStr"),
+    ("ffi", "CString"),
+    ("time", "Duration"),
+    ("time", "Instant")
+];
+
+/// Rewrites `ty` into the canonical form both indexed types and query types are compared in.
+#[inline]
+pub fn normalize(ty: Type) -> Type {
+    let config = config();
+    normalize_with(ty, config.ignore_refs, config.unwrap_ptrs)
+}
+
+fn normalize_with(ty: Type, ignore_refs: bool, unwrap_ptrs: bool) -> Type {
+    let normalize = |ty| normalize_with(ty, ignore_refs, unwrap_ptrs);
+    // Inner types first, so `Option<Bytes>` is substituted as `option<vec<u8>>` with `--equiv`.
+    let ty = match ty {
+        Type::Paren(TypeParen { elem, .. }) | Type::Group(TypeGroup { elem, .. }) => normalize(*elem),
+        // With `--strict-mut`, only shared references are stripped, `&mut T` stays apart from `T`.
+        // With `--arrays-as-slices`, `&[T; N]` is `&[T]`, before references are possibly stripped.
+        Type::Reference(mut r) if config().arrays_as_slices && matches!(*r.elem, Type::Array(..)) => {
+            let Type::Array(TypeArray { bracket_token, elem, .. }) = *r.elem else { unreachable!() };
+            r.elem = Box::new(Type::Slice(TypeSlice { bracket_token, elem }));
+            normalize(Type::Reference(r))
+        }
+        Type::Reference(TypeReference { elem, mutability, .. }) if ignore_refs && (mutability.is_none() || !config().strict_mut) => normalize(*elem),
+        // Lifetimes are local to each signature, `&'a str` is `&str` like it is to the caller.
+        Type::Reference(mut r) => {
+            r.lifetime = None;
+            r.elem = Box::new(normalize(*r.elem));
+            Type::Reference(r)
+        }
+        Type::TraitObject(TypeTraitObject { mut bounds, .. }) => {
+            bounds.iter_mut().for_each(|bound| normalize_bound(bound, ignore_refs, unwrap_ptrs));
+            Type::TraitObject(TypeTraitObject {dyn_token: Some(Dyn::default()), bounds})
+        }
+        Type::ImplTrait(TypeImplTrait { impl_token, mut bounds }) => {
+            bounds.iter_mut().for_each(|bound| normalize_bound(bound, ignore_refs, unwrap_ptrs));
+            Type::ImplTrait(TypeImplTrait {impl_token, bounds})
+        }
+        Type::Path(mut p) => {
+            if !config().no_std_paths && p.qself.is_none() { shorten_std_path(&mut p.path) }
+            if unwrap_ptrs && is_smart_pointer(&p) {
+                let Some(GenericArgument::Type(inner)) = take_single_generic(p) else { unreachable!() };
+                return normalize(inner)
+            }
+            normalize_path_args(&mut p.path, ignore_refs, unwrap_ptrs);
+            Type::Path(p)
+        }
+        Type::Tuple(mut t) => {
+            t.elems = t.elems.into_pairs().map(|pair| {
+                let (ty, punct) = pair.into_tuple();
+                Pair::new(normalize(ty), punct)
+            }).collect();
+            Type::Tuple(t)
+        }
+        Type::Slice(mut s) => {
+            s.elem = Box::new(normalize(*s.elem));
+            Type::Slice(s)
+        }
+        Type::Array(mut a) => {
+            a.elem = Box::new(normalize(*a.elem));
+            Type::Array(a)
+        }
+        Type::Ptr(mut p) => {
+            p.elem = Box::new(normalize(*p.elem));
+            Type::Ptr(p)
+        }
+        _ => ty
+    };
+    equiv::substitute(ty)
+}
+
+/// Normalizes the types in the generic arguments of `path`, and in the signature of a closure
+/// trait, so `FnMut(&str) -> bool` is normalized like the types of a function are.
+fn normalize_path_args(path: &mut syn::Path, ignore_refs: bool, unwrap_ptrs: bool) {
+    let normalize = |ty: &mut Type| {
+        *ty = normalize_with(std::mem::replace(ty, Type::Verbatim(Default::default())), ignore_refs, unwrap_ptrs)
+    };
+    path.segments.iter_mut().for_each(|seg| match seg.arguments {
+        PathArguments::AngleBracketed(ref mut args) => {
+            args.args.iter_mut().for_each(|arg| if let GenericArgument::Type(ty) = arg { normalize(ty) })
+        }
+        PathArguments::Parenthesized(ref mut args) => {
+            args.inputs.iter_mut().for_each(normalize);
+            if let ReturnType::Type(_, ref mut ty) = args.output { normalize(ty) }
+        }
+        PathArguments::None => {}
+    })
+}
+
+/// Shortens `path` to its last segment if it's one of the `STD_PATHS`. Compared case-insensitively,
+/// as `strip_refs` normalizes type strings that are already lowercased.
+fn shorten_std_path(path: &mut syn::Path) {
+    let count = path.segments.len();
+    if count < 3 || path.segments.iter().take(count - 1).any(|seg| !seg.arguments.is_none()) { return }
+    let idents = path.segments.iter().map(|seg| seg.ident.to_string()).collect::<Vec::<_>>();
+    if !["std", "core", "alloc"].iter().any(|krate| idents[0].eq_ignore_ascii_case(krate)) { return }
+    let module = idents[1..count - 1].join("::");
+    let is_std = STD_PATHS.iter().any(|(std_module, name)| {
+        std_module.eq_ignore_ascii_case(&module) && name.eq_ignore_ascii_case(&idents[count - 1])
+    });
+    if !is_std { return }
+    let Some(last) = path.segments.pop().map(Pair::into_value) else { return };
+    path.leading_colon = None;
+    path.segments = Punctuated::from_iter([last]);
+}
+
+#[inline]
+fn normalize_bound(bound: &mut TypeParamBound, ignore_refs: bool, unwrap_ptrs: bool) {
+    if let TypeParamBound::Trait(ref mut bound) = bound {
+        normalize_path_args(&mut bound.path, ignore_refs, unwrap_ptrs)
+    }
+}
+
+/// The traits the type parameters of `generics` are bounded by, in their list or in the `where`
+/// clause, by the last segment of their path: `T: serde::Serialize` gives `serialize`.
+pub fn bound_traits(generics: &Generics) -> Vec::<&'static str> {
+    let params = generics.type_params().flat_map(|param| param.bounds.iter());
+    let predicates = generics.where_clause.iter().flat_map(|clause| clause.predicates.iter()).flat_map(|pred| match pred {
+        WherePredicate::Type(pred) => Some(pred.bounds.iter()),
+        _ => None
+    }).flatten();
+    let mut traits = params.chain(predicates).filter_map(|bound| match bound {
+        TypeParamBound::Trait(bound) => bound.path.segments.last().map(|seg| seg.ident.to_string().to_lowercase()),
+        _ => None
+    }).collect::<Vec::<_>>();
+    traits.sort_unstable();
+    traits.dedup();
+    traits.into_iter().map(|tr| &*Box::leak(tr.into_boxed_str())).collect()
+}
+
+/// The traits each type parameter of `generics` is bounded by, in its list or in the `where`
+/// clause, named the way `bound_traits` names them, and its lifetime bounds, like `'static`.
+/// Parameters without any bound are left out.
+pub fn type_param_bounds(generics: &Generics) -> Vec::<(String, Vec::<&'static str>)> {
+    let params = generics.type_params().map(|param| (&param.ident, &param.bounds));
+    let predicates = generics.where_clause.iter().flat_map(|clause| clause.predicates.iter()).filter_map(|pred| {
+        let WherePredicate::Type(pred) = pred else { return None };
+        let Type::Path(ref ty) = pred.bounded_ty else { return None };
+        Some((ty.path.get_ident()?, &pred.bounds))
+    });
+    let mut param_bounds = Vec::<(String, Vec::<&'static str>)>::new();
+    params.chain(predicates).for_each(|(ident, bounds)| {
+        let traits = bounds.iter().filter_map(|bound| match bound {
+            TypeParamBound::Trait(bound) => bound.path.segments.last().map(|seg| to_static_str(&seg.ident)),
+            TypeParamBound::Lifetime(lifetime) => Some(to_static_str(lifetime)),
+            _ => None
+        });
+        let param = ident.to_string().to_lowercase();
+        match param_bounds.iter_mut().find(|(p, _)| *p == param) {
+            Some((_, traits_so_far)) => traits_so_far.extend(traits),
+            None => param_bounds.push((param, traits.collect()))
+        }
+    });
+    param_bounds.retain(|(_, traits)| !traits.is_empty());
+    param_bounds
+}
+
+/// `()` is an ordinary type wherever it appears, so `fn(())` matches `fn f(x: ())`. The only
+/// exception is a `()` return type, which `signature_get_output` turns into no return type at all,
+/// because `fn f() -> ()` and `fn f()` are the same function.
+#[inline]
+pub fn is_unit(ty: &Type) -> bool {
+    matches!(ty, Type::Tuple(t) if t.elems.is_empty())
+}
+
+/// Checks whether `ty` is a reference, looking through parens like `normalize` does.
+pub fn is_reference(ty: &Type) -> bool {
+    match ty {
+        Type::Paren(TypeParen { elem, .. }) | Type::Group(TypeGroup { elem, .. }) => is_reference(elem),
+        Type::Reference(..) => true,
+        _ => false
+    }
+}
+
+/// Checks whether `p` is `Box<T>`, `Rc<T>` or `Arc<T>`, possibly path-qualified.
+fn is_smart_pointer(p: &TypePath) -> bool {
+    if p.qself.is_some() { return false }
+    let Some(seg) = p.path.segments.last() else { return false };
+    if !SMART_POINTERS.iter().any(|ptr| seg.ident == ptr) { return false }
+    matches! {
+        seg.arguments,
+        PathArguments::AngleBracketed(ref args) if args.args.len() == 1 &&
+            matches!(args.args.first(), Some(GenericArgument::Type(..)))
+    }
+}
+
+fn take_single_generic(p: TypePath) -> Option::<GenericArgument> {
+    match p.path.segments.into_iter().last()?.arguments {
+        PathArguments::AngleBracketed(args) => args.args.into_iter().next(),
+        _ => None
+    }
+}
+
+/// Removes the whitespace `proc_macro2` puts between tokens, keeping a single space only where
+/// two words would otherwise merge, so `Vec < T >`, `Vec<T>` and `Vec <T>` all become `vec<t>`.
+pub fn canonicalize(s: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '\'';
+    let mut canonical = String::with_capacity(s.len());
+    s.split_whitespace().for_each(|token| {
+        let merges = matches! {
+            (canonical.chars().next_back(), token.chars().next()),
+            (Some(prev), Some(next)) if is_word(prev) && is_word(next)
+        };
+        if merges { canonical.push(' ') }
+        canonical.push_str(token);
+    });
+    canonical
+}
+
+/// Spaces a string `canonicalize` returned back out for display, after its commas and
+/// semicolons and around its arrows, so `result<(),io::error>` shows as `result<(), io::error>`.
+pub fn display_type(ty: &str) -> String {
+    let mut spaced = String::with_capacity(ty.len() + ty.len() / 4);
+    let mut chars = ty.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // Not before a closing delimiter, as in the one-element tuple `(u8,)`.
+            ',' | ';' if !matches!(chars.peek(), None | Some(')' | '>' | ']')) => { spaced.push(c); spaced.push(' ') }
+            '-' if chars.peek() == Some(&'>') => { chars.next(); spaced.push_str(" -> ") }
+            c => spaced.push(c)
+        }
+    }
+    spaced
+}
+
+/// Normalizes `ty` and renders it into the string indexed and query types are compared by.
+#[inline]
+pub fn type_to_string(ty: Type) -> String {
+    canonicalize(&normalize(ty).to_token_stream().to_string().to_lowercase())
+}
+
+/// Strips the references off a type string `type_to_string` returned, as if `--ignore-refs` was set.
+pub fn strip_refs(ty: &str) -> String {
+    match syn::parse_str::<Type>(ty) {
+        Ok(ty) => canonicalize(&normalize_with(ty, true, config().unwrap_ptrs).to_token_stream().to_string()),
+        Err(_) => ty.to_owned()
+    }
+}
+
+#[inline(always)]
+pub fn type_to_static_str(ty: Type) -> &'static str {
+    Box::leak(type_to_string(ty).into_boxed_str())
+}
+
+#[inline]
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'\'' || !b.is_ascii()
+}
+
+/// Checks whether the `_` at `i` in `ty` is a hole rather than part of a name or of `'_`.
+#[inline]
+fn is_hole_at(ty: &[u8], i: usize) -> bool {
+    ty[i] == b'_' &&
+        (i == 0 || !is_word_byte(ty[i - 1])) &&
+        ty.get(i + 1).is_none_or(|b| !is_word_byte(*b))
+}
+
+/// Checks whether a type string `type_to_string` returned has a `_` hole, like `vec<_>`.
+pub fn has_holes(ty: &str) -> bool {
+    (0..ty.len()).any(|i| is_hole_at(ty.as_bytes(), i))
+}
+
+/// Returns the end of the type starting at `start` in `ty`: the first `,`, `;` or unbalanced
+/// closing bracket at its nesting depth. `->` doesn't close anything.
+fn type_end(ty: &[u8], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut i = start;
+    while let Some(&b) = ty.get(i) {
+        match b {
+            b'-' if ty.get(i + 1) == Some(&b'>') => i += 1,
+            b'<' | b'(' | b'[' => depth += 1,
+            b'>' | b')' | b']' if depth == 0 => break,
+            b'>' | b')' | b']' => depth -= 1,
+            b',' | b';' if depth == 0 => break,
+            _ => {}
+        }
+        i += 1
+    }
+    i
+}
+
+/// Checks whether the self type of an `impl` block, `self_ty`, is the type a query names with
+/// `query`, regardless of its generics and of its path prefix: `duration` is `std::time::duration`
+/// and `vec` is `vec<t>`.
+pub fn self_ty_matches(query: &str, self_ty: &str) -> bool {
+    let self_ty = self_ty.split('<').next().unwrap_or_default();
+    self_ty == query || self_ty.strip_suffix(query).is_some_and(|path| path.ends_with("::"))
+}
+
+/// Returns the first generic argument of the type string `ty`, like `self` in `result<self,error>`.
+pub fn first_type_arg(ty: &str) -> Option::<&str> {
+    let start = ty.find('<')? + 1;
+    Some(&ty[start..type_end(ty.as_bytes(), start)])
+}
+
+/// The type variables of a query, single letters like `T` in `fn(Vec<T>) -> T`, in the order
+/// they are first written in `types`, as `type_to_string` renders them.
+pub fn query_vars<'a>(types: impl IntoIterator<Item = &'a str>) -> Vec::<String> {
+    let mut vars = Vec::<String>::new();
+    types.into_iter().for_each(|ty| {
+        let bytes = ty.as_bytes();
+        (0..bytes.len()).filter(|&i| bytes[i].is_ascii_alphabetic() && var_len(bytes, i, 1)).for_each(|i| {
+            let var = &ty[i..i + 1];
+            if !vars.iter().any(|v| v == var) { vars.push(var.to_owned()) }
+        })
+    });
+    vars
+}
+
+/// Checks whether the `len` bytes at `i` in `ty` are a whole type name on their own: `t::item` is
+/// an associated type rather than `t`, `foo::t` is not `t` and `'t` is a lifetime.
+#[inline]
+fn var_len(ty: &[u8], i: usize, len: usize) -> bool {
+    (i == 0 || !(is_word_byte(ty[i - 1]) || ty[i - 1] == b':')) &&
+        ty.get(i + len).is_none_or(|b| !is_word_byte(*b) && *b != b':')
+}
+
+/// Which side of a comparison a type string comes from, each one having its own variables.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Query,
+    Candidate
+}
+
+/// Variables by name, with the type they are bound to, written on its side.
+type Vars<'a> = Vec::<(&'a str, Option::<(Side, String)>)>;
+
+/// Returns the index of the variable of `vars` written at `i` in `ty`, and its length.
+fn var_at(vars: &Vars, ty: &[u8], i: usize) -> Option::<(usize, usize)> {
+    vars.iter().position(|(var, _)| {
+        ty.get(i..i + var.len()) == Some(var.as_bytes()) && var_len(ty, i, var.len())
+    }).map(|idx| (idx, vars[idx].0.len()))
+}
+
+/// The generic type parameters of a candidate signature, and the type variables of the query,
+/// which match any type, each one bound to the first type it matched so that it has to match that
+/// same type everywhere else.
+#[derive(Default, Clone)]
+pub struct Bindings<'a> {
+    vars: Vars<'a>,
+    query_vars: Vars<'a>,
+    /// How many bound variables deep the current comparison is, see `bind`.
+    depth: usize
+}
+
+impl<'a> Bindings<'a> {
+    #[inline]
+    pub fn new(generics: &'a [String]) -> Self {
+        Self {vars: generics.iter().map(|generic| (generic.as_str(), None)).collect(), query_vars: Vec::new(), depth: 0}
+    }
+
+    /// Adds the type variables of the query, see `query_vars`.
+    #[inline]
+    pub fn with_query_vars(mut self, vars: &'a [String]) -> Self {
+        self.query_vars = vars.iter().map(|var| (var.as_str(), None)).collect();
+        self
+    }
+
+    #[inline]
+    fn vars(&mut self, side: Side) -> &mut Vars<'a> {
+        match side {
+            Side::Query => &mut self.query_vars,
+            Side::Candidate => &mut self.vars
+        }
+    }
+
+    /// The types the variables are bound to, like `` `t` = `u8` ``, the generic parameters of the
+    /// candidate told apart from the type variables of the query.
+    pub fn describe(&self) -> Vec::<String> {
+        let query = self.query_vars.iter().filter_map(|(var, ty)| Some(match self.query_var_param(var)? {
+            Some(param) => format!("`{var}` = the generic `{param}`"),
+            None => format!("`{var}` = `{}`", ty.as_ref()?.1)
+        }));
+        let generics = self.vars.iter().filter_map(|(var, ty)| Some(format!("generic `{var}` = `{}`", ty.as_ref()?.1)));
+        query.chain(generics).collect()
+    }
+
+    /// The generic parameter of the candidate the query variable `var` is bound to: `None` if it's
+    /// not bound at all, `Some(None)` if it's bound to a type that is no generic parameter.
+    pub fn query_var_param(&self, var: &str) -> Option::<Option::<&str>> {
+        let (_, binding) = self.query_vars.iter().find(|(v, _)| *v == var)?;
+        let (side, ty) = binding.as_ref()?;
+        Some((*side == Side::Candidate && self.vars.iter().any(|(v, _)| v == ty)).then_some(ty.as_str()))
+    }
+
+    /// Binds the variable `var` of `side` to `ty`, or unifies the type it's bound to already with
+    /// `ty`. A variable never binds `!`, nor a type it's part of, like `t` and `vec<t>`.
+    fn bind(&mut self, side: Side, var: usize, (ty_side, ty): (Side, &[u8])) -> bool {
+        if ty_side == side && var_at(self.vars(side), ty, 0) == Some((var, ty.len())) { return true }
+        if let Some((bound_side, bound)) = self.vars(side)[var].1.clone() {
+            if self.depth >= config().type_depth_limit() { return give_up() }
+            self.depth += 1;
+            let unified = unify_sides((bound_side, bound.as_bytes()), (ty_side, ty), self);
+            self.depth -= 1;
+            return unified
+        }
+        if ty == b"!" || self.occurs((side, var), (ty_side, ty)) { return false }
+        // Type strings are made of whole chars, and `type_end` stops at ASCII only.
+        self.vars(side)[var].1 = Some((ty_side, String::from_utf8_lossy(ty).into_owned()));
+        true
+    }
+
+    /// Checks whether the variable `var` of `side` appears in `ty`, the types the variables of
+    /// `ty` are bound to included.
+    fn occurs(&mut self, (side, var): (Side, usize), (ty_side, ty): (Side, &[u8])) -> bool {
+        (0..ty.len()).any(|i| match var_at(self.vars(ty_side), ty, i) {
+            Some((other, _)) if ty_side == side && other == var => true,
+            Some((other, _)) => match self.vars(ty_side)[other].1.clone() {
+                Some((bound_side, bound)) => self.occurs((side, var), (bound_side, bound.as_bytes())),
+                None => false
+            }
+            None => false
+        })
+    }
+}
+
+/// Matches the type string `ty` against `pattern`, where every `_` hole of `pattern` matches any
+/// one type, so `vec<_>` matches `vec<u8>` and `hashmap<_,_>` matches `hashmap<string,vec<u8>>`.
+/// Both are compared as `type_to_string` renders them, which makes holes structural: a hole
+/// takes up a whole type, up to the `,` or bracket that closes it. The variables in `bindings`,
+/// those of the query in `pattern` and the generic parameters of the candidate in `ty`, are
+/// holes too, that have to match the same type everywhere: `vec<t>` matches `vec<u8>`, and `&t`
+/// matches `&u32` when `t` is generic, but `(t,t)` not `(u32,u8)`. They never match `!` though:
+/// a function returning a generic `T` is no diverging function.
+/// Types nested deeper than `--max-type-depth`, or variables bound to types binding variables that
+/// deep, are not compared at all, and so don't match.
+#[inline]
+pub fn unify(pattern: &str, ty: &str, bindings: &mut Bindings) -> bool {
+    let limit = config().type_depth_limit();
+    if nesting_depth(pattern) > limit || nesting_depth(ty) > limit { return give_up() }
+    unify_sides((Side::Query, pattern.as_bytes()), (Side::Candidate, ty.as_bytes()), bindings)
+}
+
+/// The number of comparisons given up since the last `take_given_up`, see `unify`.
+static GIVEN_UP: AtomicUsize = AtomicUsize::new(0);
+
+#[inline]
+fn give_up() -> bool {
+    GIVEN_UP.fetch_add(1, Ordering::Relaxed);
+    false
+}
+
+/// The number of comparisons given up past `--max-type-depth` since it was last called, which
+/// `--verbose` reports after each query.
+#[inline]
+pub fn take_given_up() -> usize {
+    GIVEN_UP.swap(0, Ordering::Relaxed)
+}
+
+/// How deeply the brackets of the type string `ty` nest, like 2 for `vec<option<u8>>`.
+fn nesting_depth(ty: &str) -> usize {
+    let (mut depth, mut max) = (0usize, 0);
+    let mut bytes = ty.bytes().peekable();
+    while let Some(b) = bytes.next() {
+        match b {
+            b'-' if bytes.peek() == Some(&b'>') => _ = bytes.next(),
+            b'<' | b'(' | b'[' => {
+                depth += 1;
+                max = max.max(depth)
+            }
+            b'>' | b')' | b']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max
+}
+
+#[inline]
+fn unify_sides(a: (Side, &[u8]), b: (Side, &[u8]), bindings: &mut Bindings) -> bool {
+    unify_from(a, b, (0, 0), false, bindings)
+}
+
+/// Unifies `a` and `b` from `i` in `a` and `j` in `b`, where a path of `a` starts if `suffixed`,
+/// that was matched against one of the suffixes of the path of `b` already.
+fn unify_from((a_side, a): (Side, &[u8]), (b_side, b): (Side, &[u8]), (mut i, mut j): (usize, usize), mut suffixed: bool, bindings: &mut Bindings) -> bool {
+    let suffix_paths = a_side == Side::Query && b_side == Side::Candidate && config().suffix_paths;
+    while i < a.len() || j < b.len() {
+        if suffix_paths && !suffixed && is_path_start(a, i) {
+            // The rest of the query unified with the rest of the candidate from each of the
+            // segments of its path, the whole path first.
+            return path_suffixes(b, j).any(|k| {
+                let mut attempt = bindings.clone();
+                unify_from((a_side, a), (b_side, b), (i, k), true, &mut attempt) && { *bindings = attempt; true }
+            })
+        }
+        suffixed = false;
+        if i < a.len() && is_hole_at(a, i) {
+            let end = type_end(b, j);
+            if end == j { return false }
+            (i, j) = (i + 1, end)
+        } else if j < b.len() && is_hole_at(b, j) {
+            let end = type_end(a, i);
+            if end == i { return false }
+            (i, j) = (end, j + 1)
+        } else if let Some((var, len)) = (i < a.len()).then(|| var_at(bindings.vars(a_side), a, i)).flatten() {
+            let end = type_end(b, j);
+            if end == j || !bindings.bind(a_side, var, (b_side, &b[j..end])) { return false }
+            (i, j) = (i + len, end)
+        } else if let Some((var, len)) = (j < b.len()).then(|| var_at(bindings.vars(b_side), b, j)).flatten() {
+            let end = type_end(a, i);
+            if end == i || !bindings.bind(b_side, var, (a_side, &a[i..end])) { return false }
+            (i, j) = (end, j + len)
+        } else if i < a.len() && b.get(j) == Some(&a[i]) {
+            (i, j) = (i + 1, j + 1)
+        } else {
+            return false
+        }
+    }
+    true
+}
+
+/// Checks whether a path starts at `i` in `ty`, which is not within another path.
+#[inline]
+fn is_path_start(ty: &[u8], i: usize) -> bool {
+    ty.get(i).is_some_and(|b| is_word_byte(*b)) && (i == 0 || !is_word_byte(ty[i - 1]) && ty[i - 1] != b':')
+}
+
+/// The starts of the segments of the path at `i` in `ty`, for `--suffix-paths`: `std::io::error`
+/// gives the starts of `std`, `io` and `error`. Generic arguments end the path, so only their
+/// own paths are suffixed.
+fn path_suffixes(ty: &[u8], i: usize) -> impl Iterator<Item = usize> + '_ {
+    let end = (i..ty.len()).find(|&k| !is_word_byte(ty[k]) && ty[k] != b':').unwrap_or(ty.len());
+    std::iter::once(i).chain((i..end).filter(move |&k| k > i && ty[k - 1] == b':' && ty[k] != b':'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::index::Index;
+    use crate::structdef::StructDef;
+    use crate::item::{Item, parse_query};
+
+    fn normalized(ty: &str) -> String {
+        type_to_string(syn::parse_str(ty).unwrap())
+    }
+
+    #[test]
+    fn canonical_whitespace() {
+        assert_eq!(canonicalize("Vec < T >"), "Vec<T>");
+        assert_eq!(canonicalize("HashMap < K , Vec < u8 > >"), "HashMap<K,Vec<u8>>");
+        assert_eq!(canonicalize("& 'a mut  std :: io :: Error"), "&'a mut std::io::Error");
+        assert_eq!(canonicalize("dyn Fn ( & str ) -> bool"), "dyn Fn(&str)->bool");
+        assert_eq!(normalized("Vec<T>"), normalized("Vec  <  T  >"));
+        assert_eq!(normalized("Result<Vec<u8>,io::Error>"), normalized("Result < Vec<u8> , io :: Error >"));
+        assert_eq!(normalized("&'a   mut  str"), "&mut str");
+        assert_eq!(display_type(&normalized("Result < ( ) , HashMap<K,[u8 ; 4]> >")), "result<(), hashmap<k, [u8; 4]>>");
+        assert_eq!(display_type(&normalized("(u8 ,)")), "(u8,)");
+        assert_eq!(display_type(&normalized("fn(u8,u8)->bool")), "fn(u8, u8) -> bool");
+    }
+
+    #[test]
+    fn oddly_spaced_queries() {
+        let code = "fn f<'a>(v: Vec<T>, s:&'a str)->Option<&'a str> { None }\nstruct S { m: HashMap<String,Vec<u8>> }";
+        let index = Index::from_sources([("a.rs".into(), code.to_owned())]);
+        let Item::FnSignature(query) = parse_query("fn ( Vec < T > , & 'a  str ) -> Option< & 'a str >").unwrap().item else { unreachable!() };
+        assert!(query.matches(&index.files[0].0[0].1));
+        let query = syn::parse_str::<StructDef>("struct { m : HashMap < String , Vec<u8> > }").unwrap();
+        assert!(index.files[0].1[0].1.has_fields(&query));
+    }
+}
@@ -0,0 +1,93 @@
+use std::ops::Range;
+use std::path::Path;
+
+use syn::File;
+
+use crate::config::config;
+use crate::skim::literal_end;
+
+const ITEM_STARTS: &[&str] = &[
+    "pub", "fn", "struct", "enum", "impl", "trait", "mod", "use",
+    "const", "static", "type", "unsafe", "async", "extern", "#[", "///"
+];
+
+/// Checks whether a top-level item starts at `code[i..]`, assuming `i` is at the start of a line.
+#[inline]
+fn is_item_start(code: &str, i: usize) -> bool {
+    ITEM_STARTS.iter().any(|start| {
+        code[i..].strip_prefix(start).is_some_and(|rest| {
+            start.ends_with(['[', '/']) || rest.starts_with(|c: char| c.is_whitespace() || c == '(')
+        })
+    })
+}
+
+/// Splits `code` into its top-level items: every chunk ends either with a `;` or with the `}`
+/// closing its outermost brace. Strings, chars and comments are skipped over so the delimiters
+/// inside them don't count. As the code is known to be broken, unbalanced delimiters are
+/// expected: an item starting at the beginning of a line always starts a new chunk.
+fn split_items(code: &str) -> Vec::<Range::<usize>> {
+    let bytes = code.as_bytes();
+    let (mut items, mut depth, mut start, mut i) = (Vec::new(), 0usize, 0, 0);
+    while i < bytes.len() {
+        if let Some(end) = literal_end(code, i) {
+            i = end;
+            continue
+        }
+        match bytes[i] {
+            b'{' | b'(' | b'[' => depth += 1,
+            b')' | b']' => depth = depth.saturating_sub(1),
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    items.push(start..i + 1);
+                    start = i + 1
+                }
+            }
+            b';' if depth == 0 => {
+                items.push(start..i + 1);
+                start = i + 1
+            }
+            b'\n' if depth > 0 && is_item_start(code, i + 1) => {
+                items.push(start..i + 1);
+                start = i + 1;
+                depth = 0
+            }
+            _ => {}
+        }
+        i += 1
+    }
+    items
+}
+
+/// Used when `code` as a whole fails to parse: parses every top-level item on its own, so the
+/// items that are fine still get indexed. Each item is padded with the newlines and spaces that
+/// precede it in `code`, so its spans keep their original lines and columns.
+pub fn recover_items(file_path: &Path, code: &str) -> Option::<File> {
+    let chunks = split_items(code);
+    let total = chunks.len();
+
+    let (mut lines_before, mut line_start, mut scanned) = (0, 0, 0);
+    let items = chunks.into_iter().filter_map(|chunk| {
+        let skipped = &code[scanned..chunk.start];
+        lines_before += skipped.matches('\n').count();
+        if let Some(n) = skipped.rfind('\n') { line_start = scanned + n + 1 }
+        scanned = chunk.start;
+        let columns = code[line_start..chunk.start].chars().count();
+
+        let mut padded = "\n".repeat(lines_before);
+        padded.extend(std::iter::repeat_n(' ', columns));
+        padded.push_str(&code[chunk]);
+        syn::parse_str::<File>(&padded).ok()
+    }).flat_map(|file| file.items).collect::<Vec::<_>>();
+
+    if config().verbose {
+        eprintln!{
+            "[{path}: failed to parse, recovered {recovered} of {total} items]",
+            path = file_path.display(),
+            recovered = items.len()
+        }
+    }
+
+    if items.is_empty() { return None }
+    Some(File {shebang: None, attrs: Vec::new(), items})
+}
@@ -0,0 +1,396 @@
+use std::fmt::{self, Display, Formatter, Write};
+use std::path::PathBuf;
+use std::cmp::Ordering;
+use std::fs::read_to_string;
+use std::collections::HashMap;
+
+use crate::loc::Loc;
+use crate::attrs::Attrs;
+use crate::config::SortBy;
+use crate::enumdef::EnumDef;
+use crate::constdef::ConstDef;
+use crate::fnsig::FnSignature;
+use crate::structdef::StructDef;
+use crate::traitdef::TraitDef;
+use crate::macrodef::MacroDef;
+use crate::typedef::TypeDef;
+use crate::workspace;
+use crate::highlight;
+use crate::implquery::ImplQuery;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    Fn,
+    Struct,
+    Enum,
+    Const,
+    Trait,
+    Macro,
+    Type
+}
+
+impl Kind {
+    pub const ALL: [Kind; 7] = [Kind::Fn, Kind::Struct, Kind::Enum, Kind::Const, Kind::Trait, Kind::Macro, Kind::Type];
+
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fn => "fn",
+            Self::Struct => "struct",
+            Self::Enum => "enum",
+            Self::Const => "const",
+            Self::Trait => "trait",
+            Self::Macro => "macro",
+            Self::Type => "type"
+        }
+    }
+
+    /// Parses a kind written the way `as_str` writes it.
+    #[inline]
+    pub fn parse(kind: &str) -> Option::<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == kind)
+    }
+
+    #[inline]
+    pub fn header(&self) -> &'static str {
+        match self {
+            Self::Fn => "Functions:",
+            Self::Struct => "Structs:",
+            Self::Enum => "Enums:",
+            Self::Const => "Constants:",
+            Self::Trait => "Traits:",
+            Self::Macro => "Macros:",
+            Self::Type => "Types:"
+        }
+    }
+}
+
+/// The indexed item a `Match` points at.
+#[derive(Clone, Copy)]
+pub enum ItemRef<'a> {
+    Fn(&'a FnSignature),
+    Struct(&'a StructDef),
+    Enum(&'a EnumDef),
+    Const(&'a ConstDef),
+    Trait(&'a TraitDef),
+    Macro(&'a MacroDef),
+    Type(&'a TypeDef)
+}
+
+impl<'a> ItemRef<'a> {
+    #[inline]
+    pub fn kind(&self) -> Kind {
+        match self {
+            Self::Fn(..) => Kind::Fn,
+            Self::Struct(..) => Kind::Struct,
+            Self::Enum(..) => Kind::Enum,
+            Self::Const(..) => Kind::Const,
+            Self::Trait(..) => Kind::Trait,
+            Self::Macro(..) => Kind::Macro,
+            Self::Type(..) => Kind::Type
+        }
+    }
+
+    #[inline]
+    pub fn name(&self) -> Option::<&'a str> {
+        match self {
+            Self::Fn(sig) => sig.name(),
+            Self::Struct(def) => def.name,
+            Self::Enum(def) => def.name,
+            Self::Const(def) => def.name,
+            Self::Trait(def) => def.name,
+            Self::Macro(def) => def.name,
+            Self::Type(def) => def.name
+        }
+    }
+
+    /// The traits the item's type parameters are bounded by, constants, macros and type aliases
+    /// have none.
+    #[inline]
+    pub fn bounds(&self) -> &'a [&'static str] {
+        match self {
+            Self::Fn(sig) => sig.bounds(),
+            Self::Struct(def) => &def.bounds,
+            Self::Enum(def) => &def.bounds,
+            Self::Const(..) => &[],
+            Self::Trait(def) => &def.bounds,
+            Self::Macro(..) | Self::Type(..) => &[]
+        }
+    }
+
+    /// The traits the item derives, only structs and enums derive any.
+    #[inline]
+    pub fn derives(&self) -> &'a [&'static str] {
+        match self {
+            Self::Struct(def) => &def.derives,
+            Self::Enum(def) => &def.derives,
+            _ => &[]
+        }
+    }
+
+    #[inline]
+    pub fn attrs(&self) -> Attrs {
+        match self {
+            Self::Fn(sig) => sig.attrs(),
+            Self::Struct(def) => def.attrs,
+            Self::Enum(def) => def.attrs,
+            Self::Const(def) => def.attrs,
+            Self::Trait(def) => def.attrs,
+            Self::Macro(def) => def.attrs,
+            Self::Type(def) => def.attrs
+        }
+    }
+}
+
+// Items and queries display as the plain text form they're compared in, spaced out to read, like
+// `fn connect(duration, &str) -> result<(), error>`, on one line.
+
+impl Display for FnSignature {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&highlight::render_fn(self, None))
+    }
+}
+
+impl Display for StructDef {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&highlight::render_struct(self, |_| false))
+    }
+}
+
+impl Display for EnumDef {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&highlight::render_enum(self))
+    }
+}
+
+impl Display for ConstDef {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&highlight::render_const(self))
+    }
+}
+
+impl Display for TraitDef {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&highlight::render_trait(self))
+    }
+}
+
+impl Display for MacroDef {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&highlight::render_macro(self))
+    }
+}
+
+impl Display for TypeDef {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&highlight::render_type(self))
+    }
+}
+
+impl Display for ImplQuery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.methods.is_empty() { return write!(f, "impl {name}", name = self.trait_name) }
+        let methods = self.methods.iter().map(|sig| format!("{sig};")).collect::<Vec::<_>>().join(" ");
+        write!(f, "impl {name} {{ {methods} }}", name = self.trait_name)
+    }
+}
+
+impl Display for ItemRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ItemRef::Fn(sig) => sig.fmt(f),
+            ItemRef::Struct(def) => def.fmt(f),
+            ItemRef::Enum(def) => def.fmt(f),
+            ItemRef::Const(def) => def.fmt(f),
+            ItemRef::Trait(def) => def.fmt(f),
+            ItemRef::Macro(def) => def.fmt(f),
+            ItemRef::Type(def) => def.fmt(f)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Match<'a, 'b> {
+    pub loc: &'a Loc<'b>,
+    pub item: ItemRef<'a>,
+    /// The item rendered with the parts that matched emphasized, set with `--highlight`.
+    pub highlighted: Option::<String>,
+    /// Why the item matched, set with `--explain`.
+    pub reason: Option::<String>
+}
+
+impl<'a, 'b> Match<'a, 'b> {
+    #[inline(always)]
+    pub fn new(loc: &'a Loc<'b>, item: ItemRef<'a>) -> Self {
+        Self {loc, item, highlighted: None, reason: None}
+    }
+
+    /// Orders by `sort_by` first, ties are broken by path, then line, then column.
+    pub fn cmp_by(&self, other: &Self, sort_by: SortBy) -> Ordering {
+        let primary = match sort_by {
+            SortBy::Path => Ordering::Equal,
+            SortBy::Name => self.item.name().cmp(&other.item.name()),
+            SortBy::Kind => self.item.kind().cmp(&other.item.kind())
+        };
+        primary.then_with(|| self.loc.cmp(other.loc))
+    }
+}
+
+pub type Matches<'a, 'b> = Vec::<Match<'a, 'b>>;
+
+/// Renders `matches` under the header of their kind, the default output: the item, highlighted
+/// with `--highlight`, after its location, and why it matched under it with `--explain`.
+pub fn grouped(matches: &Matches) -> String {
+    let mut out = String::new();
+    Kind::ALL.iter().for_each(|kind| {
+        let mut group = matches.iter().filter(|m| m.item.kind() == *kind).peekable();
+        if group.peek().is_none() { return }
+        _ = writeln!(out, "{header}", header = kind.header());
+        group.for_each(|m| {
+            _ = match (&m.highlighted, m.item.name()) {
+                (Some(highlighted), _) => writeln!(out, "    {loc}  {highlighted}", loc = m.loc),
+                (None, Some(name)) => writeln!(out, "    {loc}  {name}", loc = m.loc),
+                (None, None) => writeln!(out, "    {loc}", loc = m.loc)
+            };
+            if let Some(ref reason) = m.reason { _ = writeln!(out, "        matched: {reason}") }
+        })
+    });
+    out
+}
+
+/// Renders a match as a JSON object, with the rendered item too when `full` is set. `files` caches
+/// the files the byte offsets are computed against.
+pub fn match_to_json<'a>(m: &Match<'a, '_>, files: &mut HashMap::<&'a PathBuf, Option::<String>>, full: bool) -> serde_json::Value {
+    let code = files.entry(m.loc.path()).or_insert_with(|| read_to_string(m.loc.path()).ok());
+    let bytes = code.as_deref().and_then(|code| m.loc.byte_range(code));
+    let mut json = serde_json::json!({
+        "path": m.loc.display_path().display().to_string(),
+        "line": m.loc.line(),
+        "column": m.loc.column(),
+        "byte_start": bytes.as_ref().map(|b| b.start),
+        "byte_end": bytes.as_ref().map(|b| b.end),
+        "kind": m.item.kind().as_str(),
+        "name": m.item.name()
+    });
+    if full {
+        json["signature"] = m.item.to_string().into();
+        json["crate"] = workspace::crate_of(m.loc.path()).into()
+    }
+    if let Some(ref reason) = m.reason {
+        json["reason"] = reason.as_str().into()
+    }
+    json
+}
+
+/// Renders every match as a JSON object, with the rendered item too when `full` is set.
+pub fn matches_to_json(matches: &[Match], full: bool) -> Vec::<serde_json::Value> {
+    // Offsets are computed against the files as they are on disk, each one read only once.
+    let mut files = HashMap::new();
+    matches.iter().map(|m| match_to_json(m, &mut files, full)).collect()
+}
+
+/// The tab-separated line of a match, see `--porcelain`. The columns are a stable interface, new
+/// ones can only be added at the end.
+pub fn porcelain_line(m: &Match) -> String {
+    format!{
+        "{kind}\t{path}\t{line}\t{column}\t{name}\t{signature}",
+        kind = m.item.kind().as_str(),
+        path = m.loc.display_path().display(),
+        line = m.loc.line(),
+        column = m.loc.column(),
+        name = m.item.name().unwrap_or("-"),
+        signature = m.item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::index::Index;
+
+    const CODE: &str = "\
+pub fn connect(addr: &str, timeout: Duration) -> Result<TcpStream, io::Error> { todo!() }
+struct config { timeout: duration, retries: option<u8> }
+struct pair(u8, u8);
+enum shape { circle(f32), rect { w: f32, h: f32 }, empty }
+";
+
+    fn index() -> Index<'static> {
+        Index::from_sources([("a.rs".into(), CODE.to_owned())])
+    }
+
+    fn matches<'a>(index: &'a Index<'static>) -> Matches<'a, 'static> {
+        let (fnsigs, defs, edefs, ..) = &index.files[0];
+        fnsigs.iter().map(|(loc, sig)| Match::new(loc, ItemRef::Fn(sig)))
+            .chain(defs.iter().map(|(loc, def)| Match::new(loc, ItemRef::Struct(def))))
+            .chain(edefs.iter().map(|(loc, def)| Match::new(loc, ItemRef::Enum(def))))
+            .collect()
+    }
+
+    #[test]
+    fn signatures() {
+        let index = index();
+        let signatures = matches(&index).iter().map(|m| m.item.to_string()).collect::<Vec::<_>>();
+        assert_eq!(signatures, [
+            "fn connect(&str, duration) -> result<tcpstream, io::error>",
+            "struct config { timeout: duration, retries: option<u8> }",
+            "struct pair(u8, u8)",
+            "enum shape { circle(f32), rect { w: f32, h: f32 }, empty }"
+        ]);
+    }
+
+    #[test]
+    fn text() {
+        let (index, path) = (index(), "a.rs");
+        let mut matches = matches(&index);
+        matches[1].reason = Some("has a field `timeout: duration`".to_owned());
+        assert_eq!(grouped(&matches), format!("\
+Functions:
+    {path}:1:0  connect
+Structs:
+    {path}:2:0  config
+        matched: has a field `timeout: duration`
+    {path}:3:0  pair
+Enums:
+    {path}:4:0  shape
+"));
+    }
+
+    #[test]
+    fn porcelain() {
+        let (index, path) = (index(), "a.rs");
+        let lines = matches(&index).iter().map(porcelain_line).collect::<Vec::<_>>();
+        assert_eq!(lines, [
+            format!("fn\t{path}\t1\t0\tconnect\tfn connect(&str, duration) -> result<tcpstream, io::error>"),
+            format!("struct\t{path}\t2\t0\tconfig\tstruct config {{ timeout: duration, retries: option<u8> }}"),
+            format!("struct\t{path}\t3\t0\tpair\tstruct pair(u8, u8)"),
+            format!("enum\t{path}\t4\t0\tshape\tenum shape {{ circle(f32), rect {{ w: f32, h: f32 }}, empty }}")
+        ]);
+    }
+
+    #[test]
+    fn json() {
+        // The byte offsets are computed against the file on disk.
+        let path = std::env::temp_dir().join(format!("roogle-json-{pid}.rs", pid = std::process::id()));
+        std::fs::write(&path, CODE).unwrap();
+        let index = Index::from_sources([(path.clone(), CODE.to_owned())]);
+        let matches = matches(&index);
+        let (json, full) = (matches_to_json(&matches[..2], false), matches_to_json(&matches[3..], true));
+        std::fs::remove_file(&path).unwrap();
+        let path = path.display().to_string();
+        assert_eq!(serde_json::Value::Array(json), serde_json::json!([
+            {"path": path, "line": 1, "column": 0, "byte_start": 0, "byte_end": 89, "kind": "fn", "name": "connect"},
+            {"path": path, "line": 2, "column": 0, "byte_start": 90, "byte_end": 146, "kind": "struct", "name": "config"}
+        ]));
+        assert_eq!(full[0]["signature"], "enum shape { circle(f32), rect { w: f32, h: f32 }, empty }");
+        assert_eq!(full[0]["crate"], serde_json::Value::Null);
+    }
+}
@@ -0,0 +1,386 @@
+//! Builds the index from the JSON emitted by `cargo +nightly rustdoc -- --output-format json -Z unstable-options`,
+//! so crates can be searched without their sources being checked out.
+//!
+//! Every item is rendered back into Rust source and fed through the same `syn` conversions the
+//! `.rs` files go through, so queries match rustdoc items exactly like parsed ones.
+
+use std::fs;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use syn::parse::Parser;
+
+use crate::loc::Loc;
+use crate::attrs::Attrs;
+use crate::fnsig::{FnSigs, FnSignature};
+use crate::enumdef::{EnumDef, EnumDefs};
+use crate::constdef::{ConstDef, ConstDefs};
+use crate::normalize::type_to_static_str;
+use crate::structdef::{StructDef, StructDefs};
+use crate::traitdef::{TraitDef, TraitDefs};
+use crate::macrodef::{MacroDef, MacroDefs};
+use crate::typedef::{TypeDef, TypeDefs};
+
+type Index = Map::<String, Value>;
+
+pub type Items = (FnSigs<'static>, StructDefs<'static>, EnumDefs<'static>, ConstDefs<'static>, TraitDefs<'static>, MacroDefs<'static>, TypeDefs<'static>);
+
+pub fn load(path: &Path) -> Result::<Items, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("could not read `{}`: {e}", path.display()))?;
+    let json = serde_json::from_str::<Value>(&json).map_err(|e| format!("`{}`: {e}", path.display()))?;
+    let Some(index) = json.get("index").and_then(Value::as_object) else {
+        return Err(format!("`{}` is not a rustdoc JSON file", path.display()))
+    };
+
+    let (mut fnsigs, mut defs, mut edefs, mut cdefs, mut tdefs, mut mdefs, mut tydefs) = (FnSigs::new(), StructDefs::new(), EnumDefs::new(), ConstDefs::new(), TraitDefs::new(), MacroDefs::new(), TypeDefs::new());
+    let self_tys = impl_self_types(index);
+    let mut paths = HashMap::new();
+    index.values().for_each(|item| {
+        let (Some(name), Some(loc), Some((kind, inner))) = (
+            item.get("name").and_then(Value::as_str),
+            item_loc(item, &mut paths),
+            item.get("inner").and_then(item_kind)
+        ) else { return };
+
+        // The attributes are rendered along with the item, so that they're recorded like parsed ones.
+        let attrs = render_attrs(item);
+        let render = |rendered: Option::<String>| rendered.map(|rendered| attrs.clone() + &rendered);
+        match kind {
+            "function" => if let Some(f) = render(render_fn(name, inner)).and_then(|f| syn::parse_str::<syn::ItemFn>(&f).ok()) {
+                let self_ty = item.get("id").and_then(id_key).and_then(|id| self_tys.get(&id).copied());
+                fnsigs.push((loc, FnSignature::from(f).with_self_ty(self_ty)))
+            }
+            "struct" => if let Some(s) = render(render_struct(index, name, inner)).and_then(|s| syn::parse_str::<syn::ItemStruct>(&s).ok()) {
+                defs.push((loc, StructDef::from(s)))
+            }
+            "enum" => if let Some(e) = render(render_enum(index, name, inner)).and_then(|e| syn::parse_str::<syn::ItemEnum>(&e).ok()) {
+                edefs.push((loc, EnumDef::from(e)))
+            }
+            "constant" | "assoc_const" => if let Some(c) = render(render_const(name, inner)).and_then(|c| syn::parse_str::<syn::ItemConst>(&c).ok()) {
+                let mut def = ConstDef::from(c);
+                def.self_ty = item.get("id").and_then(id_key).and_then(|id| self_tys.get(&id).copied());
+                cdefs.push((loc, def))
+            }
+            "trait" => if let Some(t) = render(render_trait(index, name, inner)).and_then(|t| syn::parse_str::<syn::ItemTrait>(&t).ok()) {
+                tdefs.push((loc, TraitDef::from(t)))
+            }
+            // Only the name is indexed, so the rules are left out.
+            "macro" => if let Some(m) = render(Some(format!("macro_rules! {name} {{}}"))).and_then(|m| syn::parse_str::<syn::ItemMacro>(&m).ok()) {
+                mdefs.push((loc, MacroDef::from(m)))
+            }
+            "type_alias" => if let Some(t) = render(render_type_alias(name, inner)).and_then(|t| syn::parse_str::<syn::ItemType>(&t).ok()) {
+                tydefs.push((loc, TypeDef::from(t)))
+            }
+            "extern_type" => if let Some(t) = render(Some(format!("type {name};"))).and_then(|t| syn::parse_str::<syn::ForeignItemType>(&t).ok()) {
+                tydefs.push((loc, TypeDef::from(t)))
+            }
+            _ => {}
+        }
+    });
+
+    Ok((fnsigs, defs, edefs, cdefs, tdefs, mdefs, tydefs))
+}
+
+/// The location of `item`, its path leaked once per file and kept in `paths`, as the items of a
+/// crate are in few files.
+fn item_loc(item: &Value, paths: &mut HashMap::<String, &'static PathBuf>) -> Option::<Loc<'static>> {
+    let span = item.get("span")?;
+    let file_path = span.get("filename")?.as_str()?;
+    // rustdoc columns are 1-based, `proc_macro2`'s are 0-based.
+    let linecol = |key| -> Option::<(usize, usize)> {
+        let linecol = span.get(key)?.as_array()?;
+        let line = linecol.first()?.as_u64()? as usize;
+        let column = (linecol.get(1)?.as_u64()? as usize).saturating_sub(1);
+        Some((line, column))
+    };
+    let (start, end) = (linecol("begin")?, linecol("end")?);
+    let file_path = match paths.get(file_path) {
+        Some(path) => *path,
+        None => *paths.entry(file_path.to_owned()).or_insert(Box::leak(Box::new(PathBuf::from(file_path))))
+    };
+    Some(Loc::new(file_path, start, end))
+}
+
+/// Renders the `#[doc(hidden)]` and `#[deprecated]` attributes of `item`. rustdoc lists the
+/// former in `attrs`, as source strings, and the latter in `deprecation`.
+fn render_attrs(item: &Value) -> String {
+    let hidden = item.get("attrs").and_then(Value::as_array).is_some_and(|attrs| {
+        attrs.iter().filter_map(Value::as_str).any(|attr| {
+            syn::Attribute::parse_outer.parse_str(attr).is_ok_and(|attrs| Attrs::from(attrs.as_slice()).hidden)
+        })
+    });
+    let deprecated = item.get("deprecation").is_some_and(|d| !d.is_null());
+    format!{
+        "{hidden}{deprecated}",
+        hidden = if hidden { "#[doc(hidden)] " } else { "" },
+        deprecated = if deprecated { "#[deprecated] " } else { "" }
+    }
+}
+
+/// The kind of an item and what rustdoc records for it, which is nothing for the kinds it
+/// serializes as a bare string, like `"extern_type"`.
+#[inline]
+fn item_kind(inner: &Value) -> Option::<(&str, &Value)> {
+    match inner {
+        Value::Object(inner) => inner.iter().next().map(|(kind, inner)| (kind.as_str(), inner)),
+        Value::String(kind) => Some((kind.as_str(), &Value::Null)),
+        _ => None
+    }
+}
+
+#[inline]
+fn field_type<'a>(index: &'a Index, id: &Value) -> Option::<(&'a str, &'a Value)> {
+    let field = index.get(&id_key(id)?)?;
+    let name = field.get("name")?.as_str()?;
+    let ty = field.get("inner")?.get("struct_field")?;
+    Some((name, ty))
+}
+
+#[inline]
+fn id_key(id: &Value) -> Option::<String> {
+    match id {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None
+    }
+}
+
+fn render_fn(name: &str, inner: &Value) -> Option::<String> {
+    let sig = inner.get("sig").or_else(|| inner.get("decl"))?;
+    let inputs = sig.get("inputs")?.as_array()?.iter().filter_map(|input| {
+        let [name, ty] = input.as_array()?.as_slice() else { return None };
+        let name = name.as_str()?;
+        if name == "self" { return None }
+        let name = if syn::parse_str::<syn::Ident>(name).is_ok() { name } else { "_" };
+        Some(format!("{name}: {ty}", ty = render_type(ty)?))
+    }).collect::<Vec::<_>>().join(", ");
+    let output = match sig.get("output") {
+        Some(Value::Null) | None => String::new(),
+        Some(ty) => format!(" -> {}", render_type(ty)?)
+    };
+    Some(format!("fn {name}({inputs}){output} {{}}"))
+}
+
+/// Maps the id of every item declared in an `impl` block to the self type of that block.
+fn impl_self_types(index: &Index) -> HashMap::<String, &'static str> {
+    index.values().filter_map(|item| item.get("inner")?.get("impl")).filter_map(|im| {
+        let self_ty = syn::parse_str::<syn::Type>(&render_type(im.get("for")?)?).ok()?;
+        Some((type_to_static_str(self_ty), im.get("items")?.as_array()?))
+    }).flat_map(|(self_ty, ids)| ids.iter().filter_map(id_key).map(move |id| (id, self_ty))).collect()
+}
+
+fn render_const(name: &str, inner: &Value) -> Option::<String> {
+    let ty = render_type(inner.get("type")?)?;
+    Some(format!("const {name}: {ty} = _;"))
+}
+
+/// Renders a type alias with its type parameters, its lifetimes and consts aren't indexed.
+fn render_type_alias(name: &str, inner: &Value) -> Option::<String> {
+    let ty = render_type(inner.get("type")?)?;
+    let params = inner.get("generics")?.get("params")?.as_array()?.iter().filter_map(|param| {
+        param.get("kind")?.get("type")?;
+        param.get("name")?.as_str()
+    }).collect::<Vec::<_>>();
+    let params = if params.is_empty() { String::new() } else { format!("<{}>", params.join(", ")) };
+    Some(format!("type {name}{params} = {ty};"))
+}
+
+/// Renders a trait with its associated types only, the rest of its items aren't indexed.
+fn render_trait(index: &Index, name: &str, inner: &Value) -> Option::<String> {
+    let assoc_types = inner.get("items")?.as_array()?.iter().filter_map(|id| {
+        let item = index.get(&id_key(id)?)?;
+        let assoc = item.get("inner")?.get("assoc_type")?;
+        let tname = item.get("name")?.as_str()?;
+        match assoc.get("type").or_else(|| assoc.get("default")) {
+            Some(Value::Null) | None => Some(format!("type {tname};")),
+            Some(ty) => Some(format!("type {tname} = {};", render_type(ty)?))
+        }
+    }).collect::<String>();
+    Some(format!("trait {name} {{ {assoc_types} }}"))
+}
+
+fn render_fields(index: &Index, ids: &Value) -> Option::<String> {
+    ids.as_array()?.iter().map(|id| {
+        let (name, ty) = field_type(index, id)?;
+        Some(format!("{name}: {ty},", ty = render_type(ty)?))
+    }).collect()
+}
+
+fn render_tuple_fields(index: &Index, ids: &Value) -> Option::<String> {
+    let tys = ids.as_array()?.iter().map(|id| {
+        // Stripped (private) fields are `null`.
+        if id.is_null() { return Some("_".to_owned()) }
+        render_type(field_type(index, id)?.1)
+    }).collect::<Option::<Vec::<_>>>()?;
+    Some(tys.join(", "))
+}
+
+fn render_struct(index: &Index, name: &str, inner: &Value) -> Option::<String> {
+    match inner.get("kind")? {
+        Value::String(unit) if unit == "unit" => Some(format!("struct {name};")),
+        Value::Object(kind) => {
+            if let Some(plain) = kind.get("plain") {
+                Some(format!("struct {name} {{ {} }}", render_fields(index, plain.get("fields")?)?))
+            } else {
+                Some(format!("struct {name}({});", render_tuple_fields(index, kind.get("tuple")?)?))
+            }
+        }
+        _ => None
+    }
+}
+
+fn render_enum(index: &Index, name: &str, inner: &Value) -> Option::<String> {
+    let variants = inner.get("variants")?.as_array()?.iter().map(|id| {
+        let variant = index.get(&id_key(id)?)?;
+        let vname = variant.get("name")?.as_str()?;
+        let kind = variant.get("inner")?.get("variant")?.get("kind")?;
+        match kind {
+            Value::String(plain) if plain == "plain" => Some(format!("{vname},")),
+            Value::Object(kind) => if let Some(tuple) = kind.get("tuple") {
+                Some(format!("{vname}({}),", render_tuple_fields(index, tuple)?))
+            } else {
+                Some(format!("{vname} {{ {} }},", render_fields(index, kind.get("struct")?.get("fields")?)?))
+            }
+            _ => None
+        }
+    }).collect::<Option::<String>>()?;
+    Some(format!("enum {name} {{ {variants} }}"))
+}
+
+fn render_generic_args(args: &Value) -> Option::<String> {
+    if let Some(angle) = args.get("angle_bracketed") {
+        let mut rendered = angle.get("args")?.as_array()?.iter().map(|arg| {
+            let (kind, arg) = arg.as_object()?.iter().next()?;
+            match kind.as_str() {
+                "type" => render_type(arg),
+                "lifetime" => arg.as_str().map(ToOwned::to_owned),
+                "const" => arg.get("expr").and_then(Value::as_str).map(ToOwned::to_owned),
+                _ => Some("_".to_owned())
+            }
+        }).collect::<Option::<Vec::<_>>>()?;
+        let constraints = angle.get("constraints").or_else(|| angle.get("bindings"));
+        if let Some(constraints) = constraints.and_then(Value::as_array) {
+            rendered.extend(constraints.iter().filter_map(|c| {
+                let name = c.get("name")?.as_str()?;
+                let ty = c.get("binding")?.get("equality")?.get("type")?;
+                Some(format!("{name} = {}", render_type(ty)?))
+            }));
+        }
+        Some(if rendered.is_empty() { String::new() } else { format!("<{}>", rendered.join(", ")) })
+    } else if let Some(paren) = args.get("parenthesized") {
+        let inputs = paren.get("inputs")?.as_array()?.iter().map(render_type).collect::<Option::<Vec::<_>>>()?;
+        let output = match paren.get("output") {
+            Some(Value::Null) | None => String::new(),
+            Some(ty) => format!(" -> {}", render_type(ty)?)
+        };
+        Some(format!("({}){output}", inputs.join(", ")))
+    } else {
+        Some(String::new())
+    }
+}
+
+fn render_path(path: &Value) -> Option::<String> {
+    let name = path.get("path").or_else(|| path.get("name"))?.as_str()?;
+    let args = match path.get("args") {
+        Some(Value::Null) | None => String::new(),
+        Some(args) => render_generic_args(args)?
+    };
+    Some(format!("{name}{args}"))
+}
+
+fn render_bounds(bounds: &[Value]) -> Option::<String> {
+    let bounds = bounds.iter().map(|bound| {
+        if let Some(tr) = bound.get("trait_bound") {
+            render_path(tr.get("trait")?)
+        } else {
+            bound.get("outlives")?.as_str().map(ToOwned::to_owned)
+        }
+    }).collect::<Option::<Vec::<_>>>()?;
+    Some(bounds.join(" + "))
+}
+
+/// Renders a rustdoc JSON `Type` as Rust source.
+fn render_type(ty: &Value) -> Option::<String> {
+    if ty.as_str() == Some("infer") { return Some("_".to_owned()) }
+    let (kind, ty) = ty.as_object()?.iter().next()?;
+    let is_mut = |ty: &Value| ty.get("is_mutable").or_else(|| ty.get("mutable")).and_then(Value::as_bool).unwrap_or_default();
+    let rendered = match kind.as_str() {
+        "resolved_path" => render_path(ty)?,
+        "primitive" | "generic" => ty.as_str()?.to_owned(),
+        "borrowed_ref" => {
+            let lifetime = ty.get("lifetime").and_then(Value::as_str).map(|l| format!("{l} ")).unwrap_or_default();
+            let mutability = if is_mut(ty) { "mut " } else { "" };
+            format!("&{lifetime}{mutability}{}", render_type(ty.get("type")?)?)
+        }
+        "raw_pointer" => {
+            let mutability = if is_mut(ty) { "mut" } else { "const" };
+            format!("*{mutability} {}", render_type(ty.get("type")?)?)
+        }
+        "tuple" => {
+            let elems = ty.as_array()?.iter().map(render_type).collect::<Option::<Vec::<_>>>()?;
+            if elems.len() == 1 { format!("({},)", elems[0]) } else { format!("({})", elems.join(", ")) }
+        }
+        "slice" => format!("[{}]", render_type(ty)?),
+        "array" => format!("[{}; {}]", render_type(ty.get("type")?)?, ty.get("len")?.as_str()?),
+        "dyn_trait" => {
+            let traits = ty.get("traits")?.as_array()?.iter().map(|t| render_path(t.get("trait")?)).collect::<Option::<Vec::<_>>>()?;
+            format!("dyn {}", traits.join(" + "))
+        }
+        "impl_trait" => format!("impl {}", render_bounds(ty.as_array()?)?),
+        "qualified_path" => {
+            let name = ty.get("name")?.as_str()?;
+            let self_type = render_type(ty.get("self_type")?)?;
+            match ty.get("trait") {
+                Some(Value::Null) | None => format!("{self_type}::{name}"),
+                Some(tr) => format!("<{self_type} as {}>::{name}", render_path(tr)?)
+            }
+        }
+        "function_pointer" => {
+            let sig = ty.get("sig").or_else(|| ty.get("decl"))?;
+            let inputs = sig.get("inputs")?.as_array()?.iter().map(|input| {
+                render_type(input.as_array()?.get(1)?)
+            }).collect::<Option::<Vec::<_>>>()?;
+            let output = match sig.get("output") {
+                Some(Value::Null) | None => String::new(),
+                Some(ty) => format!(" -> {}", render_type(ty)?)
+            };
+            format!("fn({}){output}", inputs.join(", "))
+        }
+        _ => "_".to_owned()
+    };
+    Some(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn item_paths_are_interned() {
+        let item = |file: &str, line: u64| json!({"span": {"filename": file, "begin": [line, 1], "end": [line, 20]}});
+        let mut paths = HashMap::new();
+        let a = item_loc(&item("src/lib.rs", 3), &mut paths).unwrap();
+        let b = item_loc(&item("src/lib.rs", 7), &mut paths).unwrap();
+        let c = item_loc(&item("src/io.rs", 1), &mut paths).unwrap();
+        assert!(std::ptr::eq(a.path(), b.path()));
+        assert_eq!((b.line(), b.column()), (7, 0));
+        assert_eq!(c.path(), Path::new("src/io.rs"));
+        assert_eq!(paths.len(), 2);
+    }
+    #[test]
+    fn type_aliases_and_extern_types() {
+        let alias = json!({"type": {"resolved_path": {"path": "HashMap", "args": {"angle_bracketed": {"args": [
+            {"type": {"generic": "K"}}, {"type": {"primitive": "u8"}}
+        ], "constraints": []}}}}, "generics": {"params": [
+            {"name": "'a", "kind": {"lifetime": {"outlives": []}}},
+            {"name": "K", "kind": {"type": {"bounds": [], "default": null}}}
+        ], "where_predicates": []}});
+        assert_eq!(render_type_alias("Map", &alias).as_deref(), Some("type Map<K> = HashMap<K, u8>;"));
+        assert_eq!(item_kind(&json!("extern_type")), Some(("extern_type", &Value::Null)));
+        assert_eq!(item_kind(&json!({"type_alias": alias})).map(|(kind, _)| kind), Some("type_alias"));
+    }
+}
@@ -0,0 +1,274 @@
+//! Strips the bodies of functions from source code before it's parsed, at the lexer level: only
+//! signatures are indexed, and the bodies are most of the tokens `syn` would parse otherwise.
+//! Every other token keeps its line and column, so the items get the same `Loc`s either way.
+
+use std::ops::Range;
+
+/// The end of the comment, string or char literal starting at `i` in `code`, if one does, so that
+/// the delimiters in it don't count: past its closing quote, or at the end of the line for a line
+/// comment. A lifetime is no literal, a raw string can be a byte or C string, like `br"\"`.
+pub fn literal_end(code: &str, i: usize) -> Option::<usize> {
+    let bytes = code.as_bytes();
+    match bytes[i] {
+        b'/' if bytes.get(i + 1) == Some(&b'/') => Some(code[i..].find('\n').map_or(bytes.len(), |n| i + n)),
+        b'/' if bytes.get(i + 1) == Some(&b'*') => {
+            let (mut nesting, mut i) = (0, i);
+            while i < bytes.len() {
+                if bytes[i..].starts_with(b"/*") {
+                    nesting += 1;
+                    i += 2
+                } else if bytes[i..].starts_with(b"*/") {
+                    nesting -= 1;
+                    i += 2;
+                    if nesting == 0 { break }
+                } else {
+                    i += 1
+                }
+            }
+            Some(i)
+        }
+        b'r' if matches!(bytes.get(i + 1), Some(b'"' | b'#')) && (!is_ident_byte(bytes, i) || is_raw_prefix(bytes, i)) => {
+            let hashes = bytes[i + 1..].iter().take_while(|b| **b == b'#').count();
+            if bytes.get(i + 1 + hashes) != Some(&b'"') { return None }
+            let closing = format!("\"{}", "#".repeat(hashes));
+            let body = i + 2 + hashes;
+            Some(code[body..].find(&closing).map_or(bytes.len(), |n| body + n + closing.len()))
+        }
+        b'"' => {
+            let mut i = i + 1;
+            while i < bytes.len() && bytes[i] != b'"' {
+                i += if bytes[i] == b'\\' { 2 } else { 1 }
+            }
+            Some((i + 1).min(bytes.len()))
+        }
+        b'\'' if bytes.get(i + 1) == Some(&b'\\') => {
+            let mut i = i + 3;
+            while i < bytes.len() && bytes[i] != b'\'' { i += 1 }
+            Some((i + 1).min(bytes.len()))
+        }
+        b'\'' => {
+            let c = code[i + 1..].chars().next()?;
+            code[i + 1 + c.len_utf8()..].starts_with('\'').then_some(i + 2 + c.len_utf8())
+        }
+        _ => None
+    }
+}
+
+#[inline]
+pub fn is_ident_byte(bytes: &[u8], i: usize) -> bool {
+    i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_')
+}
+
+/// Checks whether the `r` at `i` follows the `b` or `c` of a raw byte or C string, like `br"`,
+/// rather than ending an identifier.
+#[inline]
+fn is_raw_prefix(bytes: &[u8], i: usize) -> bool {
+    i > 0 && matches!(bytes[i - 1], b'b' | b'c') && !is_ident_byte(bytes, i - 1)
+}
+
+/// Pushes `interior` to `out` blanked: its newlines kept so the following items stay on their
+/// lines, and its last line padded so the closing brace stays in its column.
+fn push_blanked(out: &mut String, interior: &str) {
+    out.extend(std::iter::repeat_n('\n', interior.matches('\n').count()));
+    let last_line = interior.rsplit('\n').next().unwrap_or_default();
+    out.extend(std::iter::repeat_n(' ', last_line.chars().count()));
+}
+
+/// Removes the bodies of the functions of `code`, those of the free functions, of methods and of
+/// the functions nested in anything but another body alike, blanked by `push_blanked`. A
+/// function is told from a `fn(u8)` pointer type by its name. The rules of `macro_rules!` are
+/// kept as they are, their `$` fragments being no Rust yet. Returns `None` if a signature, a body
+/// or a macro doesn't close where expected, for `code` to be parsed as it is.
+pub fn skim(code: &str) -> Option::<String> {
+    let bytes = code.as_bytes();
+    let mut skimmed = String::with_capacity(code.len() / 2);
+    let (mut prev, mut i) = (0, 0);
+    while i < bytes.len() {
+        if let Some(end) = literal_end(code, i) {
+            i = end;
+            continue
+        }
+        if bytes[i..].starts_with(b"macro_rules!") && !is_ident_byte(bytes, i) {
+            let open = i + code[i..].find(['{', '(', '['])?;
+            i = closing_delimiter(code, open)? + 1;
+            continue
+        }
+        if is_fn_item(code, i) {
+            if let Some(body) = body_of(code, i + 2)? {
+                skimmed.push_str(&code[prev..body.start]);
+                push_blanked(&mut skimmed, &code[body.clone()]);
+                (prev, i) = (body.end, body.end);
+                continue
+            }
+        }
+        i += 1
+    }
+    skimmed.push_str(&code[prev..]);
+    Some(skimmed)
+}
+
+/// Checks whether `code[i..]` starts with the `fn` of a function, followed by its name.
+#[inline]
+fn is_fn_item(code: &str, i: usize) -> bool {
+    let bytes = code.as_bytes();
+    if !bytes[i..].starts_with(b"fn") || is_ident_byte(bytes, i) { return false }
+    let rest = &code[i + 2..];
+    let name = rest.trim_start();
+    name.len() < rest.len() && name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+}
+
+/// The interior of the body of the function whose signature goes on from `start`, `None` inside
+/// if it has none, like the methods of a trait, and `None` if the signature doesn't close. The
+/// body is the first `{` out of the parens, brackets and angle brackets of the signature.
+fn body_of(code: &str, start: usize) -> Option::<Option::<Range::<usize>>> {
+    let bytes = code.as_bytes();
+    let (mut depth, mut i) = (0usize, start);
+    while i < bytes.len() {
+        if let Some(end) = literal_end(code, i) {
+            i = end;
+            continue
+        }
+        match bytes[i] {
+            b'-' | b'=' if bytes.get(i + 1) == Some(&b'>') => i += 1,
+            b'(' | b'[' | b'<' => depth += 1,
+            b'{' if depth > 0 => depth += 1,
+            b'{' => return Some(Some(i + 1..closing_delimiter(code, i)?)),
+            b')' | b']' | b'>' | b'}' => depth = depth.checked_sub(1)?,
+            b';' if depth == 0 => return Some(None),
+            _ => {}
+        }
+        i += 1
+    }
+    None
+}
+
+/// The `}`, `)` or `]` closing the `{`, `(` or `[` at `open` in `code`.
+fn closing_delimiter(code: &str, open: usize) -> Option::<usize> {
+    let bytes = code.as_bytes();
+    let (opening, closing) = match bytes[open] {
+        b'{' => (b'{', b'}'),
+        b'(' => (b'(', b')'),
+        _ => (b'[', b']')
+    };
+    let (mut depth, mut i) = (0usize, open);
+    while i < bytes.len() {
+        if let Some(end) = literal_end(code, i) {
+            i = end;
+            continue
+        }
+        match bytes[i] {
+            b if b == opening => depth += 1,
+            b if b == closing && depth == 1 => return Some(i),
+            b if b == closing => depth -= 1,
+            _ => {}
+        }
+        i += 1
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use quote::ToTokens;
+    use syn::{File, Item, ImplItem, TraitItem, spanned::Spanned};
+
+    /// Empties the bodies of the functions of `items` the way `skim` does, in `impl` blocks,
+    /// traits and modules too.
+    fn strip_bodies(items: &mut [Item]) {
+        items.iter_mut().for_each(|item| match item {
+            Item::Fn(f) => f.block.stmts.clear(),
+            Item::Impl(im) => im.items.iter_mut().for_each(|item| if let ImplItem::Fn(f) = item { f.block.stmts.clear() }),
+            Item::Trait(t) => t.items.iter_mut().for_each(|item| if let TraitItem::Fn(f) = item {
+                if let Some(block) = &mut f.default { block.stmts.clear() }
+            }),
+            Item::Mod(m) => if let Some((_, items)) = &mut m.content { strip_bodies(items) },
+            _ => {}
+        })
+    }
+
+    /// The lines and columns where `items` and the items in them start and end.
+    fn spans(items: &[Item]) -> Vec::<((usize, usize), (usize, usize))> {
+        let span = |span: proc_macro2::Span| ((span.start().line, span.start().column), (span.end().line, span.end().column));
+        items.iter().flat_map(|item| {
+            let inner = match item {
+                Item::Impl(im) => im.items.iter().map(|item| span(item.span())).collect(),
+                Item::Trait(t) => t.items.iter().map(|item| span(item.span())).collect(),
+                Item::Mod(m) => m.content.as_ref().map(|(_, items)| spans(items)).unwrap_or_default(),
+                _ => Vec::new()
+            };
+            std::iter::once(span(item.span())).chain(inner)
+        }).collect()
+    }
+
+    /// Checks that `code` skimmed parses to what it parses to with its bodies emptied, with
+    /// every item at the same place.
+    fn assert_skims(code: &str) {
+        let skimmed = skim(code).expect("skimmed");
+        let mut full = syn::parse_str::<File>(code).unwrap();
+        strip_bodies(&mut full.items);
+        let parsed = syn::parse_str::<File>(&skimmed).unwrap_or_else(|e| panic!("{e} in:\n{skimmed}"));
+        assert_eq!(parsed.to_token_stream().to_string(), full.to_token_stream().to_string());
+        assert_eq!(spans(&parsed.items), spans(&full.items));
+    }
+
+    #[test]
+    fn nested_fns() {
+        assert_skims("fn outer() -> u8 {\n    fn inner(x: u8) -> u8 { x }\n    inner(1)\n}\nfn after() {}");
+        assert_skims("mod m {\n    fn f(x: u8) { let _ = x; }\n    mod n { pub fn g() -> u8 { 1 } }\n}");
+        assert_skims("impl S {\n    fn g(&self) -> u8 {\n        fn h() -> u8 { 2 }\n        h()\n    }\n}");
+    }
+
+    #[test]
+    fn trait_methods_without_bodies() {
+        assert_skims("trait T {\n    fn a(&self) -> u8;\n    fn b(&self) -> u8 { self.a() + 1 }\n    fn c();\n}\nfn d() { todo!() }");
+    }
+
+    #[test]
+    fn fn_pointer_types() {
+        assert_skims("fn apply(f: fn(u8) -> u8, x: u8) -> u8 { f(x) }\nstruct S { f: fn(u8) }\ntype F = fn(&str) -> bool;");
+        assert_skims("fn make() -> fn() -> u8 { || 1 }\nfn g(f: &dyn Fn(u8) -> [u8; 2]) -> u8 { f(1)[0] }");
+    }
+
+    #[test]
+    fn arrows_in_where_clauses() {
+        assert_skims("fn map<F>(f: F) -> u8\nwhere\n    F: Fn(u8) -> u8,\n{\n    f(1)\n}");
+        assert_skims("fn g<F: FnOnce() -> Vec<u8>>(f: F) where F: Send { f(); }\nfn h<T>() where for<'a> &'a T: IntoIterator<Item = &'a u8> {}");
+    }
+
+    #[test]
+    fn lifetimes_and_chars() {
+        assert_skims("fn first<'a>(s: &'a str) -> &'a str {\n    let (c, d, e) = ('{', '\\'', '}');\n    &s[..1]\n}\nfn after<'b>(x: &'b u8) {}");
+        assert_skims("struct S<'a>(&'a str);\nfn f<'a>(s: S<'a>) -> char { let _ = b'{'; '\\u{7d}' }\nfn g() {}");
+    }
+
+    #[test]
+    fn braces_in_strings_and_comments() {
+        assert_skims("fn r() -> &'static str { r#\"}\"# } // }\n/* { */\nfn s() -> &'static str { \"}\\\"{\" }\nfn t() -> &'static [u8] { b\"{\" }");
+        assert_skims("fn u() { /* } /* nested } */ { */ }\n/// A doc { comment\nfn v() -> u8 { 1 }");
+    }
+
+    #[test]
+    fn raw_byte_strings() {
+        assert_skims("fn a() -> &'static [u8] { br\"\\\" }\nfn b() -> &'static [u8] { br#\"}\"# }\nfn c() {}");
+        assert_skims("fn a() -> &'static [u8] { br\"{\\\" }\nfn b() {}");
+        assert_eq!(literal_end("br\"\\\" }", 1), Some(5));
+        assert_eq!(literal_end("xbr\"\"", 2), None);
+    }
+
+    #[test]
+    fn macro_rules_bodies() {
+        let code = "macro_rules! make {\n    ($name:ident) => { fn $name() -> u8 { 1 } };\n    () => { fn made() -> u8 { 2 } };\n}\nfn after() -> u8 { 3 }";
+        assert_skims(code);
+        assert!(skim(code).unwrap().starts_with(&code[..code.find("\nfn after").unwrap()]));
+        assert_skims("macro_rules! m ( ($x:expr) => { fn f() -> u8 { $x } } );\nfn g() { m!(1); }");
+    }
+
+    #[test]
+    fn bodies_are_blanked() {
+        let skimmed = skim("fn f() -> u8 {\n    1\n}\nfn g() {}").unwrap();
+        assert_eq!(skimmed, "fn f() -> u8 {\n\n}\nfn g() {}");
+        assert_eq!(skim("fn f() {"), None);
+    }
+}
@@ -9,13 +9,24 @@ use syn::{
 };
 
 use crate::loc::Loc;
+use crate::attrs::{Attrs, derives};
+use crate::config::config;
 use crate::fields::*;
 use crate::{skip_tokens, to_static_str};
+use crate::normalize::{bound_traits, type_to_static_str};
 
 pub struct StructDef {
     pub name: Option::<&'static str>,
-    pub is_tup: bool,
-    pub fields: Fields
+    /// Whether this is a tuple struct, always known for an indexed struct. A query leaves it
+    /// unspecified when its fields are in braces and none of them is named, like in
+    /// `struct { Duration }`, so that it matches structs of any shape.
+    pub is_tup: Option::<bool>,
+    pub fields: Fields,
+    /// The traits the type parameters are bounded by, see `bound_traits`.
+    pub bounds: Vec::<&'static str>,
+    /// The traits it derives, see `derives`.
+    pub derives: Vec::<&'static str>,
+    pub attrs: Attrs
 }
 
 pub type StructDefs<'a> = Vec::<(Loc::<'a>, StructDef)>;
@@ -38,42 +49,62 @@ impl Parse for StructDef {
                 let field = parse_optionaly_named_field(&content).unwrap();
                 fields.push(field);
                 if content.is_empty() { break }
-                skip_tokens!(input, ,);
+                skip_tokens!(content, ,);
             }
+            let is_tup = if fields.iter().any(|f| f.name.is_some()) { Some(false) } else { None };
             let fields = Fields::Named(fields);
-            Ok(StructDef{name, is_tup: false, fields})
+            Ok(StructDef{name, is_tup, fields, bounds: Vec::new(), derives: Vec::new(), attrs: Attrs::default()})
         } else if lookahead.peek(Paren) {
             let content;
             syn::parenthesized!(content in input);
             let mut fields = Vec::new();
             loop {
                 if content.is_empty() { break }
+                let ty = content.parse::<Type>()?;
                 let field = Field {
                     name: None,
-                    ty: Some(to_static_str(&input.parse::<Type>()?))
+                    ty: (!matches!(ty, Type::Infer(..))).then(|| type_to_static_str(ty))
                 };
                 fields.push(field);
                 if content.is_empty() { break }
-                skip_tokens!(input, ,);
+                skip_tokens!(content, ,);
             }
             let fields = Fields::Unnamed(fields);
-            Ok(StructDef{name, is_tup: true, fields})
+            Ok(StructDef{name, is_tup: Some(true), fields, bounds: Vec::new(), derives: Vec::new(), attrs: Attrs::default()})
         } else if lookahead.peek(syn::Token![;]) {
             input.parse::<syn::Token![;]>().unwrap();
             let fields = Fields::Unit;
-            Ok(StructDef{name, is_tup: false, fields})
+            Ok(StructDef{name, is_tup: Some(false), fields, bounds: Vec::new(), derives: Vec::new(), attrs: Attrs::default()})
         } else {
             Err(lookahead.error())
         }
     }
 }
 
+impl StructDef {
+    /// Checks whether every field of `query` is present in `self`, by name, by type, or by both
+    /// when the query field has both, and its shape if the query specifies one. The other fields of
+    /// `self` are not looked at. With `--ordered-fields`, the fields of `query` also have to come
+    /// in the same order in `self`, though not necessarily next to each other.
+    pub fn has_fields(&self, query: &StructDef) -> bool {
+        if query.is_tup.is_some_and(|is_tup| self.is_tup != Some(is_tup)) { return false }
+        if config().ordered_fields {
+            let mut fields = self.fields.iter();
+            return query.fields.iter().all(|qf| fields.any(|f| qf.matches(f)))
+        }
+        query.fields.iter().all(|qf| self.fields.iter().any(|f| qf.matches(f)))
+    }
+}
+
 impl From::<syn::ItemStruct> for StructDef {
     fn from(structdef: syn::ItemStruct) -> Self {
         let name = Some(to_static_str(&structdef.ident));
-        let is_tup = matches!(structdef.fields, syn::Fields::Unnamed(_));
+        let attrs = Attrs::from(structdef.attrs.as_slice());
+        let derives = derives(&structdef.attrs);
+        let bounds = bound_traits(&structdef.generics);
+        let is_tup = Some(matches!(structdef.fields, syn::Fields::Unnamed(_)));
         let fields = Fields::from(structdef.fields);
-        Self {name, is_tup, fields}
+        Self {name, is_tup, fields, bounds, derives, attrs}
     }
 }
 
@@ -85,7 +116,7 @@ impl Debug for StructDef {
         } else {
             writeln!(f, "[null],")?
         }
-        writeln!(f, "is_tup: {is_tup},", is_tup = self.is_tup)?;
+        writeln!(f, "is_tup: {is_tup:?},", is_tup = self.is_tup)?;
         for fi in self.fields.iter() {
             let ty = fi.ty.unwrap_or_default();
             if let Some(ref name) = fi.name {
@@ -1,3 +1,4 @@
+use std::sync::OnceLock;
 use std::hash::BuildHasherDefault;
 use std::collections::{BTreeSet, HashMap, HashSet};
 
@@ -23,7 +24,9 @@ pub type Xx64Hasher = BuildHasherDefault::<XxHash64>;
 #[derive(Debug)]
 pub struct StructDefMap<'a> {
     types: Types<'a>,
-    names: Option::<Names<'a>>,
+    /// Built the first time the structs are searched by field name, type only queries never
+    /// needing it.
+    names: OnceLock::<Names<'a>>,
     field_names: BTreeSet::<&'static str>,
     all_defs: HashMap::<&'a Loc::<'a>, &'a StructDef>
 }
@@ -32,7 +35,7 @@ impl<'a> StructDefMap<'a> {
     pub fn new(defs_len: usize) -> Self {
         Self {
             types: Types::with_capacity_and_hasher(defs_len * 2, Xx64Hasher::default()),
-            names: None,
+            names: OnceLock::new(),
             all_defs: HashMap::with_capacity(defs_len),
             field_names: BTreeSet::new()
         }
@@ -50,37 +53,71 @@ impl<'a> StructDefMap<'a> {
         self.all_defs.insert(loc, def);
     }
 
+    #[inline(always)]
+    pub fn get(&self, loc: &Loc) -> Option::<&'a StructDef> {
+        self.all_defs.get(loc).copied()
+    }
+
+    /// Every struct inserted, in no particular order.
+    #[inline(always)]
+    pub fn defs(&self) -> impl Iterator<Item = (&'a Loc<'a>, &'a StructDef)> + '_ {
+        self.all_defs.iter().map(|(loc, def)| (*loc, *def))
+    }
+
     #[inline]
-    pub fn finalize(&mut self) {
-        let mut set_builder = SetBuilder::memory();
-        self.field_names.iter().for_each(|name| unsafe {
-            set_builder.insert(name).unwrap_unchecked();
-        });
-        self.names = Some(set_builder.into_set());
+    fn names(&self) -> &Names<'a> {
+        self.names.get_or_init(|| {
+            let mut set_builder = SetBuilder::memory();
+            self.field_names.iter().for_each(|name| unsafe {
+                set_builder.insert(name).unwrap_unchecked();
+            });
+            set_builder.into_set()
+        })
     }
 
+    /// Finds the structs `query` matches. The candidates come from the first field of the query
+    /// that isn't a `_: _` hole, every other field of the query then has to be present in the
+    /// candidate as well.
+    pub fn search(&self, query: &StructDef) -> Vec::<(&Loc<'_>, &'a StructDef)> {
+        let first = query.fields.iter().find(|f| f.name.is_some() || f.ty.is_some());
+        let results = if let Some(name) = first.and_then(|f| f.name) {
+            self.find_names(name, query.is_tup)
+        } else if let Some(ty) = first.and_then(|f| f.ty) {
+            self.find_types(ty, query.is_tup)
+        } else {
+            Vec::new()
+        };
+        results.into_iter()
+            .filter_map(|loc| self.get(loc).filter(|found| found.has_fields(query)).map(|found| (loc, found)))
+            .collect()
+    }
+
+    /// Finds the structs with a field of type `field_type`, of the given shape, or of any shape if
+    /// `is_tup` is `None`.
     #[inline]
-    pub fn find_types(&self, field_type: &str, is_tup: bool) -> Results {
+    pub fn find_types(&self, field_type: &str, is_tup: Option::<bool>) -> Results<'_, '_> {
         self.types.get(field_type).map(|set| {
             set.par_iter()
-                .filter(|loc| matches!(self.all_defs.get(*loc), Some(def) if def.is_tup == is_tup))
+                .filter(|loc| matches!(self.all_defs.get(*loc), Some(def) if is_tup.is_none_or(|is_tup| def.is_tup == Some(is_tup))))
                 .map(std::ops::Deref::deref)
                 .collect()
-        }).unwrap_or_else(Vec::new)
+        }).unwrap_or_default()
     }
 
-    pub fn find_names(&self, field_name: &str, is_tup: bool) -> Results {
+    /// Finds the structs with a field named `field_name`. Only named structs have field names, so
+    /// a tuple struct query finds nothing, without searching.
+    pub fn find_names(&self, field_name: &str, is_tup: Option::<bool>) -> Results<'_, '_> {
         let mut matches = Vec::new();
-        let Some(ref names) = self.names else { return matches };
+        if is_tup == Some(true) { return matches }
         let automaton = Str::new(field_name);
-        let stream = names.search(automaton);
+        let stream = self.names().search(automaton);
         let Ok(names) = stream.into_stream().into_strs() else { return matches };
         for name in names {
             matches.par_extend(
                 self.all_defs.par_iter().filter_map(|(loc, def)| {
-                    if def.is_tup != is_tup { return None }
-                    let Some(iter) = def.fields.par_iter() else { return None };
-                    if iter.any(|f| f.name.map_or(false, |i| i == name)) {
+                    if def.is_tup != Some(false) { return None }
+                    let iter = def.fields.par_iter()?;
+                    if iter.any(|f| f.name.is_some_and(|i| i == name)) {
                         Some(loc)
                     } else {
                         None
@@ -90,3 +127,62 @@ impl<'a> StructDefMap<'a> {
         } matches
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::index::Index;
+    use crate::item::{Item, parse_query};
+
+    const CODE: &str = "struct Config { timeout: Duration, retries: u8 }\nstruct Pair(Duration, u8);";
+
+    fn names_of(map: &StructDefMap, results: Results) -> Vec::<&'static str> {
+        let mut names = results.into_iter().filter_map(|loc| map.get(loc)?.name).collect::<Vec::<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn names_are_built_by_name_queries_only() {
+        let index = Index::from_sources([("a.rs".into(), CODE.to_owned())]);
+        let mut map = StructDefMap::new(2);
+        index.files.iter().flat_map(|(_, defs, ..)| defs).for_each(|(loc, def)| map.insert(def, loc));
+        assert_eq!(names_of(&map, map.find_types("duration", None)), ["config", "pair"]);
+        assert!(map.names.get().is_none());
+        assert_eq!(names_of(&map, map.find_names("timeout", None)), ["config"]);
+        assert!(map.names.get().is_some());
+    }
+
+    #[test]
+    fn shapes_and_queries() {
+        let index = Index::from_sources([("a.rs".into(), CODE.to_owned())]);
+        let mut map = StructDefMap::new(2);
+        index.files.iter().flat_map(|(_, defs, ..)| defs).for_each(|(loc, def)| map.insert(def, loc));
+        // Tuple structs have no field names, only types.
+        assert_eq!(names_of(&map, map.find_names("retries", Some(true))), Vec::<&str>::new());
+        assert!(map.names.get().is_none());
+        assert_eq!(names_of(&map, map.find_types("u8", Some(true))), ["pair"]);
+        // Named structs, by name and by type.
+        assert_eq!(names_of(&map, map.find_names("retries", Some(false))), ["config"]);
+        assert_eq!(names_of(&map, map.find_types("u8", Some(false))), ["config"]);
+    }
+
+    #[test]
+    fn each_struct_is_found_once() {
+        let index = Index::from_sources([
+            ("a.rs".into(), CODE.to_owned()),
+            ("b.rs".into(), "struct Other { timeout: Duration }".to_owned())
+        ]);
+        let mut map = StructDefMap::new(3);
+        index.files.iter().flat_map(|(_, defs, ..)| defs).for_each(|(loc, def)| map.insert(def, loc));
+        let found = |query: &str| {
+            let Item::StructDef(query) = parse_query(query).unwrap().item else { panic!("expected a struct query") };
+            let mut names = map.search(&query).into_iter().filter_map(|(_, def)| def.name).collect::<Vec::<_>>();
+            names.sort_unstable();
+            names
+        };
+        assert_eq!(found("struct { timeout: Duration, retries: u8 }"), ["config"]);
+        assert_eq!(found("struct { _: Duration, _: u8 }"), ["config", "pair"]);
+    }
+}
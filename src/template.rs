@@ -0,0 +1,89 @@
+//! The output templates of `--template`, like `{path}:{line}:{col} {kind} {name}`, parsed once at
+//! startup so that a mistake in one is reported before anything is searched.
+
+use std::borrow::Cow;
+
+/// A value of a match a template can have, written `{path}`, `{line}` and so on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placeholder {
+    Path,
+    Line,
+    /// The 0-based column, as in the text and JSON output.
+    Column,
+    /// The 1-based column, as editors count them.
+    Col,
+    Kind,
+    Name,
+    Sig,
+    Crate,
+    Reason
+}
+
+impl Placeholder {
+    const ALL: [(&'static str, Placeholder); 9] = [
+        ("path", Self::Path),
+        ("line", Self::Line),
+        ("column", Self::Column),
+        ("col", Self::Col),
+        ("kind", Self::Kind),
+        ("name", Self::Name),
+        ("sig", Self::Sig),
+        ("crate", Self::Crate),
+        ("reason", Self::Reason)
+    ];
+
+    fn from_name(name: &str) -> Option::<Self> {
+        Self::ALL.iter().find(|(n, _)| *n == name).map(|(_, placeholder)| *placeholder)
+    }
+}
+
+#[derive(Debug)]
+enum Piece {
+    Text(String),
+    Value(Placeholder)
+}
+
+#[derive(Debug)]
+pub struct Template(Vec::<Piece>);
+
+impl Template {
+    /// Parses `template`, where `{{` and `}}` stand for literal braces, and `\t` and `\n` for a
+    /// tab and a line break, erroring on placeholders that don't exist and on unclosed braces.
+    pub fn parse(template: &str) -> Result::<Self, String> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => { chars.next(); text.push('{') }
+                '}' if chars.as_str().starts_with('}') => { chars.next(); text.push('}') }
+                '\\' if chars.as_str().starts_with('t') => { chars.next(); text.push('\t') }
+                '\\' if chars.as_str().starts_with('n') => { chars.next(); text.push('\n') }
+                '{' => {
+                    let Some((name, rest)) = chars.as_str().split_once('}') else {
+                        return Err(format!("unclosed `{{` in template `{template}`"))
+                    };
+                    let Some(placeholder) = Placeholder::from_name(name) else {
+                        let names = Placeholder::ALL.iter().map(|(n, _)| format!("`{{{n}}}`")).collect::<Vec::<_>>().join(", ");
+                        return Err(format!("unknown placeholder `{{{name}}}` in template, expected one of {names}"))
+                    };
+                    if !text.is_empty() { pieces.push(Piece::Text(std::mem::take(&mut text))) }
+                    pieces.push(Piece::Value(placeholder));
+                    chars = rest.chars()
+                }
+                '}' => return Err(format!("unmatched `}}` in template `{template}`, write `}}}}` for a brace")),
+                c => text.push(c)
+            }
+        }
+        if !text.is_empty() { pieces.push(Piece::Text(text)) }
+        Ok(Template(pieces))
+    }
+
+    /// Renders the template with the values `value` gives, an empty string for a missing one.
+    pub fn render<'a>(&self, value: impl Fn(Placeholder) -> Option::<Cow::<'a, str>>) -> String {
+        self.0.iter().map(|piece| match piece {
+            Piece::Text(text) => Cow::Borrowed(text.as_str()),
+            Piece::Value(placeholder) => value(*placeholder).unwrap_or_default()
+        }).collect()
+    }
+}
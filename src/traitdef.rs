@@ -0,0 +1,108 @@
+use syn::{
+    Type,
+    Ident,
+    Token,
+    ItemTrait,
+    TraitItem,
+    token::Brace,
+    parse::{Parse, ParseStream}
+};
+
+use crate::loc::Loc;
+use crate::attrs::Attrs;
+use crate::{skip_tokens, to_static_str};
+use crate::normalize::{bound_traits, type_to_static_str};
+
+/// An associated type declared in a trait, like `type Output;` or `type Item = u8;`.
+#[derive(Debug)]
+pub struct AssocType {
+    pub name: Option::<&'static str>,
+    pub default: Option::<&'static str>
+}
+
+impl AssocType {
+    /// Checks whether `other` matches `self` as a query, where a missing name or default is a hole.
+    #[inline]
+    pub fn matches(&self, other: &AssocType) -> bool {
+        self.name.is_none_or(|name| other.name == Some(name)) &&
+            self.default.is_none_or(|default| other.default == Some(default))
+    }
+}
+
+/// A trait, or a query for one: `trait Iterator`, `trait { type Output; }` and
+/// `trait _ { type Item = u8; }` all work, the associated types of a query being a subset.
+#[derive(Debug)]
+pub struct TraitDef {
+    pub name: Option::<&'static str>,
+    pub assoc_types: Vec::<AssocType>,
+    /// The traits the type parameters are bounded by, see `bound_traits`.
+    pub bounds: Vec::<&'static str>,
+    pub attrs: Attrs
+}
+
+pub type TraitDefs<'a> = Vec::<(Loc<'a>, TraitDef)>;
+
+impl TraitDef {
+    /// Checks whether `other` matches `self` as a query: its name, if the query has one, and
+    /// every associated type of the query has to be declared in `other`.
+    pub fn matches(&self, other: &TraitDef) -> bool {
+        self.name.is_none_or(|name| other.name == Some(name)) &&
+            self.assoc_types.iter().all(|query| other.assoc_types.iter().any(|ty| query.matches(ty)))
+    }
+}
+
+impl From::<ItemTrait> for TraitDef {
+    fn from(tr: ItemTrait) -> Self {
+        let assoc_types = tr.items.into_iter().filter_map(|item| match item {
+            TraitItem::Type(ty) => Some(AssocType {
+                name: Some(to_static_str(&ty.ident)),
+                default: ty.default.map(|(_, ty)| type_to_static_str(ty))
+            }),
+            _ => None
+        }).collect();
+        Self {
+            name: Some(to_static_str(&tr.ident)),
+            assoc_types,
+            bounds: bound_traits(&tr.generics),
+            attrs: Attrs::from(tr.attrs.as_slice())
+        }
+    }
+}
+
+impl Parse for TraitDef {
+    fn parse(input: ParseStream) -> syn::Result::<Self> {
+        skip_tokens!(input, trait);
+        let name = if input.parse::<Token![_]>().is_ok() {
+            None
+        } else {
+            input.parse::<Ident>().ok().map(|ident| to_static_str(&ident))
+        };
+
+        let mut assoc_types = Vec::new();
+        if input.peek(Brace) {
+            let content;
+            syn::braced!(content in input);
+            while !content.is_empty() {
+                content.parse::<Token![type]>()?;
+                let name = if content.parse::<Token![_]>().is_ok() {
+                    None
+                } else {
+                    Some(to_static_str(&content.parse::<Ident>()?))
+                };
+                let default = if content.parse::<Token![=]>().is_ok() {
+                    match content.parse::<Type>()? {
+                        Type::Infer(..) => None,
+                        ty => Some(type_to_static_str(ty))
+                    }
+                } else {
+                    None
+                };
+                assoc_types.push(AssocType {name, default});
+                if content.parse::<Token![;]>().is_err() { break }
+            }
+            if !content.is_empty() { return Err(content.error("expected `;`")) }
+        }
+
+        Ok(TraitDef {name, assoc_types, bounds: Vec::new(), attrs: Attrs::default()})
+    }
+}
@@ -0,0 +1,149 @@
+use syn::{
+    Type,
+    Ident,
+    Token,
+    Generics,
+    ItemType,
+    GenericParam,
+    ForeignItemType,
+    parse::{Parse, ParseStream}
+};
+
+use crate::loc::Loc;
+use crate::attrs::Attrs;
+use crate::{skip_tokens, to_static_str};
+use crate::normalize::{unify, query_vars, Bindings, type_to_static_str};
+
+/// A type alias, like `type Map<K> = HashMap<K, Vec<u8>>;`, an extern type, like the `type Handle;`
+/// of an `extern` block, or a query for either: `type Map`, `type Map<K>`, `type _ = Vec<u8>` and
+/// `type _ = HashMap<K, _>` all work, the single letters of the aliased type being type variables.
+#[derive(Debug)]
+pub struct TypeDef {
+    pub name: Option::<&'static str>,
+    /// The type parameters of the alias, lowercased, lifetimes and consts left out.
+    pub params: Vec::<String>,
+    /// The type the alias stands for, normalized, `None` for an extern type.
+    pub ty: Option::<&'static str>,
+    pub attrs: Attrs
+}
+
+pub type TypeDefs<'a> = Vec::<(Loc<'a>, TypeDef)>;
+
+impl TypeDef {
+    /// Checks whether `other` matches `self` as a query: its name and its number of type
+    /// parameters if the query gives them, and the type it stands for if the query has one, which
+    /// an extern type never matches. The parameters of `other` match any type.
+    pub fn matches(&self, other: &TypeDef) -> bool {
+        self.name.is_none_or(|name| other.name == Some(name)) &&
+            (self.params.is_empty() || self.params.len() == other.params.len()) &&
+            self.ty.is_none_or(|ty| other.ty.is_some_and(|other_ty| {
+                let vars = query_vars([ty]);
+                unify(ty, other_ty, &mut Bindings::new(&other.params).with_query_vars(&vars))
+            }))
+    }
+
+    /// Whether this is an extern type rather than an alias.
+    #[inline(always)]
+    pub fn is_extern(&self) -> bool {
+        self.ty.is_none()
+    }
+}
+
+fn type_params(generics: &Generics) -> Vec::<String> {
+    generics.params.iter().filter_map(|param| match param {
+        GenericParam::Type(param) => Some(param.ident.to_string().to_lowercase()),
+        _ => None
+    }).collect()
+}
+
+impl From::<ItemType> for TypeDef {
+    #[inline]
+    fn from(item: ItemType) -> Self {
+        Self {
+            name: Some(to_static_str(&item.ident)),
+            params: type_params(&item.generics),
+            ty: Some(type_to_static_str(*item.ty)),
+            attrs: Attrs::from(item.attrs.as_slice())
+        }
+    }
+}
+
+impl From::<ForeignItemType> for TypeDef {
+    #[inline]
+    fn from(item: ForeignItemType) -> Self {
+        Self {
+            name: Some(to_static_str(&item.ident)),
+            params: type_params(&item.generics),
+            ty: None,
+            attrs: Attrs::from(item.attrs.as_slice())
+        }
+    }
+}
+
+impl Parse for TypeDef {
+    fn parse(input: ParseStream) -> syn::Result::<Self> {
+        skip_tokens!(input, type);
+        let name = if input.parse::<Token![_]>().is_ok() {
+            None
+        } else {
+            input.parse::<Ident>().ok().map(|ident| to_static_str(&ident))
+        };
+        let params = type_params(&input.parse::<Generics>()?);
+
+        let ty = if input.parse::<Token![=]>().is_ok() {
+            match input.parse::<Type>()? {
+                Type::Infer(..) => None,
+                ty => Some(type_to_static_str(ty))
+            }
+        } else {
+            None
+        };
+        skip_tokens!(input, ;);
+
+        Ok(TypeDef {name, params, ty, attrs: Attrs::default()})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::Index;
+    use crate::item::{Item, parse_query};
+
+    const CODE: &str = "
+        type Map<K> = HashMap<K, Vec<u8>>;
+        type Bytes = Vec<u8>;
+        pub type Pair<'a, T> = (&'a T, &'a T);
+        extern \"C\" { type Handle; fn close(h: *mut Handle); }
+    ";
+
+    /// The names of the type aliases and extern types of `CODE` the type query `query` matches.
+    fn found(query: &str) -> Vec::<&'static str> {
+        let Item::TypeDef(query) = parse_query(query).unwrap().item else { panic!("expected a type query") };
+        let index = Index::from_sources([("a.rs".into(), CODE.to_owned())]);
+        index.files[0].6.iter().filter(|(_, def)| query.matches(def)).filter_map(|(_, def)| def.name).collect()
+    }
+
+    #[test]
+    fn aliases_and_extern_types() {
+        let index = Index::from_sources([("a.rs".into(), CODE.to_owned())]);
+        let defs = index.files[0].6.iter().map(|(loc, def)| (loc.line(), def.name.unwrap(), def.params.clone(), def.ty)).collect::<Vec::<_>>();
+        assert_eq!(defs, [
+            (2, "map", vec!["k".to_owned()], Some("hashmap<k,vec<u8>>")),
+            (3, "bytes", vec![], Some("vec<u8>")),
+            (4, "pair", vec!["t".to_owned()], Some("(&t,&t)")),
+            (5, "handle", vec![], None)
+        ]);
+        assert!(index.files[0].6[3].1.is_extern());
+    }
+
+    #[test]
+    fn queries() {
+        assert_eq!(found("type Map"), ["map"]);
+        assert_eq!(found("type Handle"), ["handle"]);
+        assert_eq!(found("type _<T>"), ["map", "pair"]);
+        assert_eq!(found("type _ = Vec<u8>"), ["bytes"]);
+        assert_eq!(found("type _ = HashMap<String, Vec<u8>>"), ["map"]);
+        assert_eq!(found("type _ = HashMap<K, _>"), ["map"]);
+        assert_eq!(found("type _").len(), 4);
+    }
+}
@@ -0,0 +1,74 @@
+use std::{env, fs};
+use std::sync::{Mutex, OnceLock};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::Value;
+
+/// Asks `cargo metadata` for the packages of the workspace the current directory is in, and
+/// returns the directories their targets' sources are in, like `src` and `examples`, for
+/// `--workspace` to walk instead of the current directory. Dependencies are not in there, and
+/// neither is `target`. Directories under the current one are returned relative to it, like
+/// the walk from `.` gives them, so the results print the same.
+pub fn source_roots() -> Result::<Vec::<PathBuf>, String> {
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = Command::new(cargo)
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .output()
+        .map_err(|e| format!("could not run `cargo metadata`: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().find(|line| !line.trim().is_empty()).unwrap_or("`cargo metadata` failed");
+        return Err(format!("`--workspace` needs a Cargo project: {reason}"))
+    }
+    let metadata = serde_json::from_slice::<Value>(&output.stdout)
+        .map_err(|e| format!("could not parse the output of `cargo metadata`: {e}"))?;
+
+    let mut roots = metadata.get("packages").and_then(Value::as_array).into_iter().flatten()
+        .flat_map(|package| package.get("targets").and_then(Value::as_array).into_iter().flatten())
+        .filter_map(|target| Path::new(target.get("src_path")?.as_str()?).parent().map(Path::to_owned))
+        .collect::<Vec::<_>>();
+    // `src/bin` is walked as part of `src` already.
+    roots.sort_unstable();
+    roots.dedup_by(|root, prev| root.starts_with(prev));
+
+    let cwd = env::current_dir().ok();
+    Ok(roots.into_iter().map(|root| {
+        match cwd.as_deref().and_then(|cwd| root.strip_prefix(cwd).ok()) {
+            Some(relative) => Path::new(".").join(relative),
+            None => root
+        }
+    }).collect())
+}
+
+/// The name of the crate the file at `path` belongs to, for `--crate`: the package name of the
+/// nearest `Cargo.toml` above it, so that the crates of a directory holding several of them are
+/// told apart without `cargo metadata`. A virtual manifest, one without a `[package]`, owns no
+/// crate. Looked up once per directory.
+pub fn crate_of(path: &Path) -> Option::<&'static str> {
+    static CRATES: OnceLock::<Mutex::<HashMap::<PathBuf, Option::<&'static str>>>> = OnceLock::new();
+    let dir = path.parent()?;
+    let mut crates = CRATES.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(name) = crates.get(dir) { return *name }
+    let name = fs::canonicalize(dir).ok().and_then(|dir| {
+        let manifest = dir.ancestors().map(|dir| dir.join("Cargo.toml")).find(|manifest| manifest.is_file())?;
+        let name = package_name(&fs::read_to_string(manifest).ok()?)?;
+        Some(&*Box::leak(name.into_boxed_str()))
+    });
+    crates.insert(dir.to_owned(), name);
+    name
+}
+
+/// The `name` of the `[package]` table of a `Cargo.toml`, read line by line rather than parsed.
+fn package_name(manifest: &str) -> Option::<String> {
+    let mut in_package = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]"
+        } else if let Some(value) = line.strip_prefix("name").map(str::trim_start).and_then(|rest| rest.strip_prefix('=')) {
+            if in_package { return Some(value.trim().trim_matches(['"', '\'']).to_owned()) }
+        }
+    }
+    None
+}